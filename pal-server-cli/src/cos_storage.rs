@@ -0,0 +1,270 @@
+use std::fmt;
+
+use opendal::{
+    Buffer, Operator,
+    services::{Cos, S3},
+};
+use serde::Deserialize;
+
+/// save archives shared by every machine running this cli, `[cos_storage]` in config file
+#[derive(Deserialize, Clone)]
+pub struct CosStorageConfig {
+    #[serde(default)]
+    pub provider: ObjectProvider,
+    /// `{name}-{appid}` for cos
+    pub bucket: String,
+    /// bucket region, e.g. `ap-guangzhou`
+    pub region: String,
+    /// `https://cos.{region}.myqcloud.com` for cos if not set, required for s3
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    #[serde(default = "default_root")]
+    pub root: String,
+    /// keys allowed to read and write the bucket. the `tcc_config` credentials are never used here:
+    /// with `[assume_role]` they only allow `sts:AssumeRole`, env / profile credentials aren't in the config
+    pub secret_id: String,
+    pub secret_key: String,
+}
+
+impl fmt::Debug for CosStorageConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CosStorageConfig")
+            .field("provider", &self.provider)
+            .field("bucket", &self.bucket)
+            .field("region", &self.region)
+            .field("endpoint", &self.endpoint)
+            .field("root", &self.root)
+            .field("secret_id", &self.secret_id)
+            .finish_non_exhaustive()
+    }
+}
+
+fn default_root() -> String {
+    "/psm/".to_owned()
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ObjectProvider {
+    /// tencent cloud object storage
+    #[default]
+    Cos,
+    /// any s3 compatible service, e.g. a local minio
+    S3,
+}
+
+/// archives are kept as `/saves/{server}/{save_name}`
+#[derive(Debug)]
+pub struct CosStorage {
+    op: Operator,
+}
+
+impl CosStorage {
+    pub fn new(config: &CosStorageConfig) -> anyhow::Result<Self> {
+        let (secret_id, secret_key) = (config.secret_id.as_str(), config.secret_key.as_str());
+        let op = match config.provider {
+            ObjectProvider::Cos => {
+                let endpoint = config
+                    .endpoint
+                    .clone()
+                    .unwrap_or_else(|| format!("https://cos.{}.myqcloud.com", config.region));
+                let cos = Cos::default()
+                    .root(&config.root)
+                    .bucket(&config.bucket)
+                    .endpoint(&endpoint)
+                    .secret_id(secret_id)
+                    .secret_key(secret_key);
+                Operator::new(cos)?.finish()
+            }
+            ObjectProvider::S3 => {
+                let endpoint = config
+                    .endpoint
+                    .as_deref()
+                    .ok_or_else(|| anyhow::anyhow!("cos_storage.endpoint is required for s3"))?;
+                let s3 = S3::default()
+                    .root(&config.root)
+                    .bucket(&config.bucket)
+                    .region(&config.region)
+                    .endpoint(endpoint)
+                    .access_key_id(secret_id)
+                    .secret_access_key(secret_key);
+                Operator::new(s3)?.finish()
+            }
+        };
+        Ok(Self { op })
+    }
+
+    pub async fn upload_save(&self, server: &str, save_name: &str, content: Buffer) -> anyhow::Result<()> {
+        self.op.write(&format!("/saves/{server}/{save_name}"), content).await?;
+        Ok(())
+    }
+
+    pub async fn download_save(&self, server: &str, save_name: &str) -> anyhow::Result<Buffer> {
+        Ok(self.op.read(&format!("/saves/{server}/{save_name}")).await?)
+    }
+
+    /// name of the newest archive of `server`, they are named by time
+    pub async fn latest_save(&self, server: &str) -> anyhow::Result<Option<String>> {
+        let entries = self.op.list(&format!("/saves/{server}/")).await?;
+        Ok(entries
+            .iter()
+            .map(|e| e.name())
+            .filter(|name| name.ends_with(".tar.gz"))
+            .max()
+            .map(ToOwned::to_owned))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        sync::{Arc, Mutex},
+    };
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::*;
+
+    type Objects = Arc<Mutex<BTreeMap<String, Vec<u8>>>>;
+
+    /// in-memory s3 stand-in for path style PutObject / GetObject / HeadObject / ListObjectsV2
+    async fn start_s3() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let objects = Objects::default();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, objects.clone()));
+            }
+        });
+        url
+    }
+
+    async fn serve(mut stream: TcpStream, objects: Objects) {
+        let mut buf = vec![];
+        let mut chunk = [0u8; 4096];
+        let header_end = loop {
+            let n = stream.read(&mut chunk).await.unwrap();
+            if n == 0 {
+                return;
+            }
+            buf.extend_from_slice(&chunk[..n]);
+            if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                break pos + 4;
+            }
+        };
+        let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+        let mut request_line = head.lines().next().unwrap().split(' ');
+        let (method, target) = (request_line.next().unwrap(), request_line.next().unwrap());
+        let content_length = head
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(k, _)| k.eq_ignore_ascii_case("content-length"))
+            .and_then(|(_, v)| v.trim().parse::<usize>().ok())
+            .unwrap_or_default();
+        while buf.len() < header_end + content_length {
+            let n = stream.read(&mut chunk).await.unwrap();
+            buf.extend_from_slice(&chunk[..n]);
+        }
+
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        // `/{bucket}/{key}`
+        let key = path
+            .trim_start_matches('/')
+            .split_once('/')
+            .map(|(_, key)| decode(key))
+            .unwrap_or_default();
+        let (status, body) = {
+            let mut objects = objects.lock().unwrap();
+            match method {
+                "PUT" => {
+                    objects.insert(key, buf[header_end..].to_vec());
+                    ("200 OK", vec![])
+                }
+                "GET" if query.contains("list-type=2") => {
+                    let prefix = query
+                        .split('&')
+                        .find_map(|p| p.strip_prefix("prefix="))
+                        .map(decode)
+                        .unwrap_or_default();
+                    let contents: String = objects
+                        .iter()
+                        .filter(|(k, _)| k.starts_with(&prefix))
+                        .map(|(k, v)| {
+                            format!(
+                                "<Contents><Key>{k}</Key><LastModified>2024-01-25T12:00:00.000Z</LastModified>\
+                                 <ETag>\"etag\"</ETag><Size>{}</Size></Contents>",
+                                v.len()
+                            )
+                        })
+                        .collect();
+                    let xml = format!(
+                        "<?xml version=\"1.0\" encoding=\"UTF-8\"?><ListBucketResult><Prefix>{prefix}</Prefix>\
+                         <IsTruncated>false</IsTruncated>{contents}</ListBucketResult>"
+                    );
+                    ("200 OK", xml.into_bytes())
+                }
+                _ => match objects.get(&key) {
+                    Some(object) => ("200 OK", object.clone()),
+                    None => ("404 Not Found", b"<Error><Code>NoSuchKey</Code></Error>".to_vec()),
+                },
+            }
+        };
+        let head = format!(
+            "HTTP/1.1 {status}\r\ncontent-length: {}\r\nlast-modified: Thu, 25 Jan 2024 12:00:00 GMT\r\n\
+             etag: \"etag\"\r\nconnection: close\r\n\r\n",
+            body.len()
+        );
+        stream.write_all(head.as_bytes()).await.unwrap();
+        if method != "HEAD" {
+            stream.write_all(&body).await.unwrap();
+        }
+        stream.shutdown().await.ok();
+    }
+
+    fn decode(s: &str) -> String {
+        let mut out = vec![];
+        let mut bytes = s.bytes();
+        while let Some(b) = bytes.next() {
+            match b {
+                b'%' => {
+                    let hex: String = bytes.by_ref().take(2).map(char::from).collect();
+                    out.push(u8::from_str_radix(&hex, 16).unwrap());
+                }
+                b => out.push(b),
+            }
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_s3_round_trip() {
+        let config = CosStorageConfig {
+            provider: ObjectProvider::S3,
+            bucket: "psm".to_owned(),
+            region: "us-east-1".to_owned(),
+            endpoint: Some(start_s3().await),
+            root: default_root(),
+            secret_id: "ak".to_owned(),
+            secret_key: "sk".to_owned(),
+        };
+        let storage = CosStorage::new(&config).unwrap();
+        assert_eq!(storage.latest_save("test").await.unwrap(), None);
+
+        for (server, name, content) in [
+            ("test", "2024-01-25_12-00-00.tar.gz", "old"),
+            ("test", "2024-01-26_12-00-00.tar.gz", "new"),
+            ("other", "2024-01-27_12-00-00.tar.gz", "other"),
+        ] {
+            storage.upload_save(server, name, Buffer::from(content)).await.unwrap();
+        }
+        let latest = storage.latest_save("test").await.unwrap();
+        assert_eq!(latest.as_deref(), Some("2024-01-26_12-00-00.tar.gz"));
+        let content = storage.download_save("test", &latest.unwrap()).await.unwrap();
+        assert_eq!(content.to_vec(), b"new");
+    }
+}
@@ -1,53 +1,233 @@
 use std::time::Duration;
 
-use itertools::Itertools;
+use serde::Deserialize;
 use tencent_cloud_sdk::{
-    client::{TencentCloudClient, cvm::cvm_instance::{InstanceState, Price}},
+    client::{
+        TencentCloudClient,
+        cvm::cvm_image::ImageState,
+        cvm::cvm_instance::{
+            Instance, InstanceChargeType, InstanceState, InternetChargeType, LatestOperationState, Price,
+            RunInstancesRequest, SpotInstanceType,
+        },
+    },
     constant::{InstanceType, Region},
+    error::ErrorKind,
 };
 use tokio::time::{Instant, sleep};
 
-use crate::server_status::ServiceInstanceType;
+use crate::{
+    data_disk::DataDiskConfig,
+    dns::DnsConfig,
+    security_group::SecurityGroupConfig,
+    server_status::{BakedImage, ServiceInstanceType},
+    snapshot::SnapshotConfig,
+};
+
+/// how spot instances are launched, `[launch]` in config file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LaunchConfig {
+    /// regions to search for the cheapest zone, checked against DescribeRegions
+    pub regions: Vec<Region>,
+    /// same id in every region, only for public images, `image_platform` / `image_os` are used if not set
+    pub image_id: Option<String>,
+    /// newest public image of this platform ...
+    pub image_platform: String,
+    /// ... whose os name contains this ...
+    pub image_os: String,
+    /// ... for this cpu architecture
+    pub image_architecture: String,
+    /// the api default if not set
+    pub system_disk_type: Option<String>,
+    /// GB
+    pub system_disk_size: u32,
+    pub internet_charge_type: InternetChargeType,
+    /// Mbps
+    pub bandwidth: u32,
+    pub security_group: SecurityGroupConfig,
+    /// disk storage mode if set, tar archives over sftp otherwise
+    pub data_disk: Option<DataDiskConfig>,
+    /// snapshot before `--stop` if set
+    pub snapshot: Option<SnapshotConfig>,
+    /// records of the servers with a `--hostname`
+    pub dns: DnsConfig,
+}
+
+impl Default for LaunchConfig {
+    fn default() -> Self {
+        Self {
+            regions: vec![Region::Nanjing, Region::Shanghai, Region::Guangzhou],
+            image_id: None,
+            image_platform: "Ubuntu".to_owned(),
+            image_os: "22.04".to_owned(),
+            image_architecture: "x86_64".to_owned(),
+            system_disk_type: None,
+            system_disk_size: 20,
+            internet_charge_type: InternetChargeType::TRAFFIC_POSTPAID_BY_HOUR,
+            bandwidth: 10,
+            security_group: SecurityGroupConfig::default(),
+            data_disk: None,
+            snapshot: None,
+            dns: DnsConfig::default(),
+        }
+    }
+}
 
-/// return cheapest (price, (region, zone, instance_type))
+impl LaunchConfig {
+    /// everything that affects the price, key pairs and security groups are added before launch
+    pub fn request(
+        &self,
+        name: &str,
+        zone: &str,
+        instance_type: InstanceType,
+        image_id: &str,
+        max_price: Option<f64>,
+    ) -> RunInstancesRequest {
+        let request = RunInstancesRequest::new(zone, instance_type);
+        let request = match max_price {
+            Some(max_price) => request.spot(max_price, Some(SpotInstanceType::OneTime)),
+            None => request.instance_charge_type(InstanceChargeType::SPOTPAID),
+        };
+        request
+            .image_id(image_id)
+            .system_disk(self.system_disk_type.clone(), self.system_disk_size)
+            .bandwidth(self.internet_charge_type, self.bandwidth)
+            .instance_name(format!("psm-{name}"))
+            .tag("app", "palworld")
+            .tag("psm-server", name)
+    }
+}
+
+/// `image_id` of config, or the public image matching `image_platform` / `image_os` in `region`
+pub async fn resolve_image(
+    client: &TencentCloudClient,
+    region: &Region,
+    launch: &LaunchConfig,
+) -> anyhow::Result<String> {
+    if let Some(image_id) = &launch.image_id {
+        return Ok(image_id.clone());
+    }
+    let image = client
+        .cvm()
+        .images()
+        .find_public_image(
+            region,
+            &launch.image_platform,
+            &launch.image_os,
+            &launch.image_architecture,
+        )
+        .await?
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "no public {} {} {} image in {region}",
+                launch.image_platform,
+                launch.image_os,
+                launch.image_architecture
+            )
+        })?;
+    tracing::debug!("{region} image: {} {}", image.image_id, image.os_name);
+    Ok(image.image_id)
+}
+
+/// the baked image of `region` once it is ready, or [`resolve_image`]
+pub async fn resolve_baked_image(
+    client: &TencentCloudClient,
+    region: &Region,
+    launch: &LaunchConfig,
+    images: &[BakedImage],
+) -> anyhow::Result<String> {
+    if let Some(baked) = images.iter().find(|i| &i.region == region) {
+        match client.cvm().images().describe_image(region, &baked.image_id).await? {
+            Some(image) if image.image_state.is_ready() => return Ok(baked.image_id.clone()),
+            image => println!(
+                "Baked image {} in {region} is not ready ({:?}), use public image",
+                baked.image_id,
+                image.map(|i| i.image_state)
+            ),
+        }
+    }
+    resolve_image(client, region, launch).await
+}
+
+/// the configured regions, failing on any unknown or unavailable one
+pub async fn candidate_regions(client: &TencentCloudClient, regions: &[Region]) -> anyhow::Result<Vec<Region>> {
+    let available = client.cvm().regions().describe_regions().await?;
+    for region in regions {
+        if !available.iter().any(|r| &r.region == region && r.is_available()) {
+            anyhow::bail!("region {region} is unknown or unavailable");
+        }
+    }
+    Ok(regions.to_vec())
+}
+
+/// return (price, (region, request)) sorted by price, cheapest first,
+/// zones whose instance price is above `max_price` are left out, baked `images` are used where ready
 pub async fn query_spot_paid_price(
     client: &TencentCloudClient,
     candidate_regions: &[Region],
     instance_type: &ServiceInstanceType,
-) -> anyhow::Result<Vec<(Price, (Region, String, InstanceType))>> {
-    let candidate_instance_type = instance_type.to_list();
+    launch: &LaunchConfig,
+    name: &str,
+    max_price: Option<f64>,
+    images: &[BakedImage],
+) -> anyhow::Result<Vec<(Price, (Region, RunInstancesRequest))>> {
+    let (cpu, memory) = instance_type.spec();
 
     let mut handles = vec![];
 
     for region in candidate_regions {
-        let zones = client.cvm().zone().describe_zone(region).await?;
-        if let Some(zones) = zones {
-            for (zone, instance_type) in zones.iter().cartesian_product(candidate_instance_type.iter()) {
-                let client = client.clone();
-                let zone = zone.clone();
-                let region = region.clone();
-                let instance_type = instance_type.clone();
-                tokio::time::sleep(Duration::from_millis(100)).await;
-                handles.push(tokio::spawn(async move {
-                    (
-                        client
-                            .cvm()
-                            .instances()
-                            .query_price(&region, &zone, &instance_type)
-                            .await,
-                        region,
-                        zone,
-                        instance_type,
-                    )
-                }));
-            }
+        let image_id = resolve_baked_image(client, region, launch, images).await?;
+        let zones = client.cvm().zone().describe_zone(region).await?.unwrap_or_default();
+        // every type of this tier currently sold as spot, per available zone
+        let candidates = client
+            .cvm()
+            .instance_types()
+            .spot_instance_types(region, cpu, memory)
+            .await?
+            .into_iter()
+            .filter(|c| zones.contains(&c.zone))
+            .collect::<Vec<_>>();
+        tracing::debug!(
+            "{region} {instance_type:?} candidates: {:?}",
+            candidates
+                .iter()
+                .map(|c| format!("{}/{}", c.zone, c.instance_type))
+                .collect::<Vec<_>>()
+        );
+        for candidate in candidates {
+            let client = client.clone();
+            let region = region.clone();
+            let request = launch.request(name, &candidate.zone, candidate.instance_type, &image_id, max_price);
+            handles.push(tokio::spawn(async move {
+                (
+                    client.cvm().instances().query_price(&region, &request).await,
+                    region,
+                    request,
+                )
+            }));
         }
     }
 
     let mut price_result = vec![];
     for handle in handles {
-        if let (Ok(price), region, zone, instance_type) = handle.await? {
-            price_result.push((price, (region, zone, instance_type)));
+        match handle.await? {
+            (Ok(price), region, request)
+                if max_price.is_some_and(|max_price| price.instance_price.unit_price_discount > max_price) =>
+            {
+                println!(
+                    "Skip {region}/{}/{}, price {} is above max price {max_price:?}",
+                    request.placement.zone, request.instance_type, price.instance_price.unit_price_discount
+                );
+            }
+            (Ok(price), region, request) => price_result.push((price, (region, request))),
+            (Err(e), ..) if e.kind() == ErrorKind::Auth => return Err(e.into()),
+            (Err(e), region, request) => {
+                tracing::debug!(
+                    "query price failed at {region}/{}/{}: {e}",
+                    request.placement.zone,
+                    request.instance_type
+                );
+            }
         }
     }
     price_result.sort_by(|a, b| {
@@ -55,7 +235,13 @@ pub async fn query_spot_paid_price(
             .unit_price_discount
             .total_cmp(&b.0.instance_price.unit_price_discount)
     });
-    println!("All spot price results: {:#?}", price_result);
+    println!(
+        "All spot price results: {:#?}",
+        price_result
+            .iter()
+            .map(|(price, (_, request))| (&request.placement.zone, &request.instance_type, price))
+            .collect::<Vec<_>>()
+    );
     Ok(price_result)
     // price_result.into_iter().next().ok_or(anyhow::anyhow!(
     //     "failed to get any available instance of {candidate_instance_type:?}"
@@ -63,34 +249,115 @@ pub async fn query_spot_paid_price(
 }
 
 pub async fn query_cvm_ip(client: &TencentCloudClient, region: &Region, instance_id: &str) -> anyhow::Result<String> {
-    let timeout_duration = Duration::from_secs(62);
+    let instance = wait_instance_state(
+        client,
+        region,
+        instance_id,
+        InstanceState::RUNNING,
+        Duration::from_secs(62),
+    )
+    .await?;
+    instance
+        .public_ip()
+        .map(ToOwned::to_owned)
+        .ok_or(anyhow::anyhow!("running cvm without ip?"))
+}
+
+/// poll until the instance reaches `state`
+pub async fn wait_instance_state(
+    client: &TencentCloudClient,
+    region: &Region,
+    instance_id: &str,
+    state: InstanceState,
+    timeout_duration: Duration,
+) -> anyhow::Result<Instance> {
     let start_time = Instant::now();
 
     loop {
-        // 进行轮询查询的操作
-        // ...
-        let resp = client.cvm().instances().describe_instance(region).await?;
+        let instance = client.cvm().instances().describe_instance(region, instance_id).await?;
 
-        if let Some(instance) = resp
-            .response
-            .instance_set
-            .into_iter()
-            .find(|i| i.instance_id == instance_id && i.instance_state == InstanceState::RUNNING)
-        {
-            break instance
-                .public_ip_addresses
-                .ok_or(anyhow::anyhow!(format!("running cvm without ip?")))?
-                .into_iter()
-                .next()
-                .ok_or(anyhow::anyhow!(format!("running cvm without ip?")));
+        // a just created instance may not be listed yet
+        if let Some(instance) = instance {
+            if instance.instance_state == state {
+                break Ok(instance);
+            }
+            if instance.instance_state == InstanceState::LAUNCH_FAILED {
+                anyhow::bail!("cvm {instance_id} launch failed");
+            }
+            tracing::debug!(
+                "waiting cvm {instance_id} to be {state}, now {}",
+                instance.instance_state
+            );
         }
 
         // 检查是否超时
         if Instant::now() - start_time >= timeout_duration {
-            break Err(anyhow::anyhow!("query cvm create status and ip timeout"));
+            break Err(anyhow::anyhow!("wait cvm {instance_id} to be {state} timeout"));
         }
 
         // 等待一段时间再进行下一次轮询
         sleep(Duration::from_secs(5)).await;
     }
 }
+
+/// poll until the operation of `request_id` on the instance is done and the instance is in the state it implies
+pub async fn wait_latest_operation(
+    client: &TencentCloudClient,
+    region: &Region,
+    instance_id: &str,
+    request_id: &str,
+    timeout_duration: Duration,
+) -> anyhow::Result<Instance> {
+    let start_time = Instant::now();
+
+    loop {
+        let instance = client
+            .cvm()
+            .instances()
+            .describe_instance(region, instance_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("cvm {instance_id} not found in {region}"))?;
+
+        if instance.latest_operation_request_id.as_deref() == Some(request_id) {
+            let action = instance.latest_operation.as_deref().unwrap_or_default();
+            // the state may lag behind a finished operation
+            let settled = InstanceState::after(action).is_none_or(|state| state == instance.instance_state);
+            match instance.latest_operation_state {
+                Some(LatestOperationState::SUCCESS) if settled => break Ok(instance),
+                Some(LatestOperationState::FAILED) => anyhow::bail!("{action} on cvm {instance_id} failed"),
+                _ => {}
+            }
+        }
+
+        if Instant::now() - start_time >= timeout_duration {
+            break Err(anyhow::anyhow!(
+                "wait operation {request_id} on cvm {instance_id} timeout"
+            ));
+        }
+        sleep(Duration::from_secs(5)).await;
+    }
+}
+
+/// poll until the image can be launched
+pub async fn wait_image_ready(
+    client: &TencentCloudClient,
+    region: &Region,
+    image_id: &str,
+    timeout_duration: Duration,
+) -> anyhow::Result<()> {
+    let start_time = Instant::now();
+
+    loop {
+        let image = client.cvm().images().describe_image(region, image_id).await?;
+        match image.map(|i| i.image_state) {
+            Some(state) if state.is_ready() => break Ok(()),
+            Some(ImageState::CREATEFAILED) => anyhow::bail!("create image {image_id} failed"),
+            state => tracing::debug!("waiting image {image_id} to be ready, now {state:?}"),
+        }
+
+        if Instant::now() - start_time >= timeout_duration {
+            break Err(anyhow::anyhow!("wait image {image_id} timeout"));
+        }
+        sleep(Duration::from_secs(10)).await;
+    }
+}
@@ -0,0 +1,127 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+use tencent_cloud_sdk::{
+    client::{
+        TencentCloudClient,
+        cbs::cbs_disk::{CreateDisksRequest, DiskState},
+    },
+    constant::Region,
+};
+use tokio::time::{Instant, sleep};
+
+use crate::server_status::SaveDisk;
+
+/// disk storage mode, `[launch.data_disk]` in config file.
+/// each server owns a data disk holding `Pal/Saved`, tar archives are only made by `--save`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DataDiskConfig {
+    /// e.g. `CLOUD_PREMIUM`, `CLOUD_SSD`, `CLOUD_BSSD`
+    pub disk_type: String,
+    /// GB
+    pub disk_size: u32,
+}
+
+impl Default for DataDiskConfig {
+    fn default() -> Self {
+        Self {
+            disk_type: "CLOUD_PREMIUM".to_owned(),
+            disk_size: 20,
+        }
+    }
+}
+
+/// a new pay-as-you-go disk for the server `name`
+pub async fn create_save_disk(
+    client: &TencentCloudClient,
+    name: &str,
+    region: &Region,
+    zone: &str,
+    config: &DataDiskConfig,
+) -> anyhow::Result<SaveDisk> {
+    let request = CreateDisksRequest::new(zone, &config.disk_type, config.disk_size)
+        .disk_name(format!("psm-{name}"))
+        .tag("app", "palworld")
+        .tag("psm-server", name)
+        .client_token(format!(
+            "psm-disk-{}-{}",
+            name,
+            std::time::UNIX_EPOCH.elapsed().unwrap_or_default().as_millis()
+        ));
+    let disk_id = client.cbs().disks().create_disk(region, &request).await?;
+    wait_disk_state(
+        client,
+        region,
+        &disk_id,
+        DiskState::UNATTACHED,
+        Duration::from_secs(120),
+    )
+    .await?;
+    println!("Created data disk {} in {}", disk_id, zone);
+    Ok(SaveDisk {
+        region: region.clone(),
+        zone: zone.to_owned(),
+        disk_id,
+    })
+}
+
+/// attach the disk to `instance_id`, once it is released by the last instance
+pub async fn attach_save_disk(client: &TencentCloudClient, disk: &SaveDisk, instance_id: &str) -> anyhow::Result<()> {
+    let current = client
+        .cbs()
+        .disks()
+        .describe_disk(&disk.region, &disk.disk_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("data disk {} not found in {}", disk.disk_id, disk.region))?;
+    if current.attached && current.instance_id == instance_id {
+        return Ok(());
+    }
+    // still detaching from a just terminated instance
+    wait_disk_state(
+        client,
+        &disk.region,
+        &disk.disk_id,
+        DiskState::UNATTACHED,
+        Duration::from_secs(180),
+    )
+    .await?;
+    client
+        .cbs()
+        .disks()
+        .attach_disk(&disk.region, &disk.disk_id, instance_id)
+        .await?;
+    wait_disk_state(
+        client,
+        &disk.region,
+        &disk.disk_id,
+        DiskState::ATTACHED,
+        Duration::from_secs(120),
+    )
+    .await?;
+    Ok(())
+}
+
+/// poll until the disk reaches `state`
+pub async fn wait_disk_state(
+    client: &TencentCloudClient,
+    region: &Region,
+    disk_id: &str,
+    state: DiskState,
+    timeout_duration: Duration,
+) -> anyhow::Result<()> {
+    let start_time = Instant::now();
+
+    loop {
+        let disk = client.cbs().disks().describe_disk(region, disk_id).await?;
+        match disk.map(|d| d.disk_state) {
+            Some(current) if current == state => break Ok(()),
+            current => tracing::debug!("waiting disk {disk_id} to be {state}, now {current:?}"),
+        }
+
+        if Instant::now() - start_time >= timeout_duration {
+            break Err(anyhow::anyhow!("wait disk {disk_id} to be {state} timeout"));
+        }
+        sleep(Duration::from_secs(3)).await;
+    }
+}
@@ -0,0 +1,95 @@
+use serde::Deserialize;
+use tencent_cloud_sdk::client::{TencentCloudClient, dnspod::dnspod_record::RecordRequest};
+
+/// A records of `--hostname`, `[launch.dns]` in config file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DnsConfig {
+    /// the dnspod domain hostnames belong to, their last two labels if not set
+    pub domain: Option<String>,
+    /// seconds, short so players follow a new ip soon
+    pub ttl: u32,
+    /// value of the record while the server is stopped or paused
+    pub placeholder: String,
+}
+
+impl Default for DnsConfig {
+    fn default() -> Self {
+        Self {
+            domain: None,
+            ttl: 60,
+            placeholder: "127.0.0.1".to_owned(),
+        }
+    }
+}
+
+impl DnsConfig {
+    /// `(domain, sub_domain)` of `hostname`, `@` for the domain itself
+    fn split<'a>(&'a self, hostname: &'a str) -> anyhow::Result<(&'a str, &'a str)> {
+        let hostname = hostname.trim_end_matches('.');
+        if let Some(domain) = self.domain.as_deref().map(|d| d.trim_end_matches('.')) {
+            if hostname.eq_ignore_ascii_case(domain) {
+                return Ok((domain, "@"));
+            }
+            // `get` instead of slicing, `at` may fall inside a multibyte char
+            let sub_domain = hostname
+                .len()
+                .checked_sub(domain.len())
+                .filter(|&at| {
+                    hostname
+                        .get(at..)
+                        .is_some_and(|suffix| suffix.eq_ignore_ascii_case(domain))
+                })
+                .and_then(|at| hostname[..at].strip_suffix('.'))
+                .filter(|sub_domain| !sub_domain.is_empty());
+            return sub_domain
+                .map(|sub_domain| (domain, sub_domain))
+                .ok_or_else(|| anyhow::anyhow!("hostname {} is not under {}", hostname, domain));
+        }
+        match hostname.rmatch_indices('.').nth(1) {
+            Some((at, _)) => Ok((&hostname[at + 1..], &hostname[..at])),
+            None if hostname.contains('.') => Ok((hostname, "@")),
+            None => Err(anyhow::anyhow!("hostname {} has no domain", hostname)),
+        }
+    }
+}
+
+/// upsert the A record of `hostname` to `ip`
+pub async fn point_hostname(
+    client: &TencentCloudClient,
+    hostname: &str,
+    ip: &str,
+    config: &DnsConfig,
+) -> anyhow::Result<()> {
+    let (domain, sub_domain) = config.split(hostname)?;
+    let request = RecordRequest::new(domain, sub_domain, "A", ip).ttl(config.ttl);
+    let record_id = client.dnspod().records().upsert_record(&request).await?;
+    tracing::debug!("record {record_id} of {hostname} points to {ip}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split() {
+        let config = DnsConfig {
+            domain: Some("example.com".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(config.split("pal.example.com").unwrap(), ("example.com", "pal"));
+        assert_eq!(config.split("a.pal.Example.COM.").unwrap(), ("example.com", "a.pal"));
+        assert_eq!(config.split("example.com").unwrap(), ("example.com", "@"));
+        assert!(config.split("palexample.com").is_err());
+        assert!(config.split("pal.example.org").is_err());
+        assert!(config.split(".example.com").is_err());
+        assert!(config.split("xéxample.com").is_err());
+
+        let config = DnsConfig::default();
+        assert_eq!(config.split("pal.example.com.").unwrap(), ("example.com", "pal"));
+        assert_eq!(config.split("a.pal.example.com").unwrap(), ("example.com", "a.pal"));
+        assert_eq!(config.split("example.com").unwrap(), ("example.com", "@"));
+        assert!(config.split("localhost").is_err());
+    }
+}
@@ -0,0 +1,112 @@
+use std::time::Duration;
+
+use tencent_cloud_sdk::{
+    client::{
+        TencentCloudClient,
+        cvm::cvm_address::{AddressStatus, AllocateAddressesRequest},
+    },
+    constant::Region,
+};
+use tokio::time::{Instant, sleep};
+
+use crate::server_status::ServerEip;
+
+/// a new eip for the server `name`, it is kept until `--release-eip`
+pub async fn allocate_server_eip(
+    client: &TencentCloudClient,
+    name: &str,
+    region: &Region,
+) -> anyhow::Result<ServerEip> {
+    let request = AllocateAddressesRequest::new()
+        .address_name(format!("psm-{name}"))
+        .tag("app", "palworld")
+        .tag("psm-server", name);
+    let address_id = client
+        .cvm()
+        .addresses()
+        .allocate_addresses(region, &request)
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("no eip allocated in {region}"))?;
+    wait_address_state(
+        client,
+        region,
+        &address_id,
+        AddressStatus::UNBIND,
+        Duration::from_secs(60),
+    )
+    .await?;
+    let address = client
+        .cvm()
+        .addresses()
+        .describe_address(region, &address_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("eip {address_id} not found in {region}"))?;
+    println!("Allocated eip {} ({}) in {}", address.address_ip, address_id, region);
+    Ok(ServerEip {
+        region: region.clone(),
+        address_id,
+        address_ip: address.address_ip,
+    })
+}
+
+/// bind the eip to `instance_id`, once it is released by the last instance
+pub async fn bind_server_eip(client: &TencentCloudClient, eip: &ServerEip, instance_id: &str) -> anyhow::Result<()> {
+    let address = client
+        .cvm()
+        .addresses()
+        .describe_address(&eip.region, &eip.address_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("eip {} not found in {}", eip.address_id, eip.region))?;
+    if address.address_status == AddressStatus::BIND && address.instance_id.as_deref() == Some(instance_id) {
+        return Ok(());
+    }
+    // still bound to a just terminated instance
+    wait_address_state(
+        client,
+        &eip.region,
+        &eip.address_id,
+        AddressStatus::UNBIND,
+        Duration::from_secs(180),
+    )
+    .await?;
+    client
+        .cvm()
+        .addresses()
+        .associate_address(&eip.region, &eip.address_id, instance_id)
+        .await?;
+    wait_address_state(
+        client,
+        &eip.region,
+        &eip.address_id,
+        AddressStatus::BIND,
+        Duration::from_secs(120),
+    )
+    .await?;
+    Ok(())
+}
+
+/// poll until the eip reaches `state`
+pub async fn wait_address_state(
+    client: &TencentCloudClient,
+    region: &Region,
+    address_id: &str,
+    state: AddressStatus,
+    timeout_duration: Duration,
+) -> anyhow::Result<()> {
+    let start_time = Instant::now();
+
+    loop {
+        let address = client.cvm().addresses().describe_address(region, address_id).await?;
+        match address.map(|a| a.address_status) {
+            Some(current) if current == state => break Ok(()),
+            current => tracing::debug!("waiting eip {address_id} to be {state}, now {current:?}"),
+        }
+
+        if Instant::now() - start_time >= timeout_duration {
+            break Err(anyhow::anyhow!("wait eip {address_id} to be {state} timeout"));
+        }
+        sleep(Duration::from_secs(3)).await;
+    }
+}
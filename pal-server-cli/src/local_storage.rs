@@ -1,7 +1,7 @@
 use std::{io::Read, net::TcpStream, path::Path};
 
 use opendal::{
-    Operator,
+    Buffer, Operator,
     services::{Fs, Sftp},
 };
 use serde::Deserialize;
@@ -17,6 +17,36 @@ pub struct LocalSaveStorageConfig {
 pub struct SshConfig {
     pub prikey: String,
     pub user: String,
+    /// `{prikey}.pub` if not set
+    #[serde(default)]
+    pub pubkey: Option<String>,
+    /// name of the imported key pair
+    #[serde(default = "default_key_name")]
+    pub key_name: String,
+}
+
+fn default_key_name() -> String {
+    "psm_cli".to_owned()
+}
+
+impl SshConfig {
+    /// public half of `prikey`, from `pubkey` or derived by `ssh-keygen -y`
+    pub fn public_key(&self) -> anyhow::Result<String> {
+        let pubkey = self.pubkey.clone().unwrap_or_else(|| format!("{}.pub", self.prikey));
+        if let Ok(key) = std::fs::read_to_string(&pubkey) {
+            return Ok(key.trim().to_owned());
+        }
+        let output = std::process::Command::new("ssh-keygen")
+            .args(["-y", "-f", &self.prikey])
+            .output()?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "no {pubkey} and ssh-keygen -y failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+        Ok(String::from_utf8(output.stdout)?.trim().to_owned())
+    }
 }
 
 #[derive(Debug)]
@@ -28,6 +58,9 @@ impl LocalStorage {
     pub fn new(config: LocalSaveStorageConfig) -> Self {
         Self { config }
     }
+    pub fn ssh(&self) -> &SshConfig {
+        &self.config.ssh
+    }
     fn build_local_op(&self) -> anyhow::Result<Operator> {
         let fs = Fs::default().root(&self.config.local_dir);
         Ok(Operator::new(fs)?.finish())
@@ -52,6 +85,8 @@ impl LocalStorage {
             "restore_save.sh",
             "start_server.sh",
             "backup_save.sh",
+            "mount_disk.sh",
+            "unmount_disk.sh",
         ];
         for file in files {
             let content = local_op.read(&format!("/scripts/{}", file)).await?;
@@ -78,6 +113,21 @@ impl LocalStorage {
         Ok(())
     }
 
+    pub async fn has_save(&self, save_name: &str) -> anyhow::Result<bool> {
+        Ok(self.build_local_op()?.exists(&format!("/saves/{}", save_name)).await?)
+    }
+
+    pub async fn read_save(&self, save_name: &str) -> anyhow::Result<Buffer> {
+        Ok(self.build_local_op()?.read(&format!("/saves/{}", save_name)).await?)
+    }
+
+    pub async fn write_save(&self, save_name: &str, content: Buffer) -> anyhow::Result<()> {
+        self.build_local_op()?
+            .write(&format!("/saves/{}", save_name), content)
+            .await?;
+        Ok(())
+    }
+
     pub async fn get_heartbeat(&self, ip: &str) -> anyhow::Result<bool> {
         let user = &self.config.ssh.user;
         let prikey_path = &self.config.ssh.prikey;
@@ -107,16 +157,18 @@ impl LocalStorage {
             .then(|| println!("ssh2 authed"))
             .ok_or(anyhow::anyhow!("ssh2 auth failed"))?;
 
-        let script_name = match script {
-            Script::InstallServer => "install_server.sh",
-            Script::RestoreSave => "restore_save.sh",
-            Script::StartServer => "start_server.sh",
-            Script::BackupSave => "backup_save.sh",
+        let (script_name, args) = match script {
+            Script::InstallServer => ("install_server.sh", String::new()),
+            Script::RestoreSave => ("restore_save.sh", String::new()),
+            Script::StartServer => ("start_server.sh", String::new()),
+            Script::BackupSave => ("backup_save.sh", String::new()),
+            Script::MountDisk(disk_id) => ("mount_disk.sh", disk_id),
+            Script::UnmountDisk => ("unmount_disk.sh", String::new()),
         };
 
         let mut channel = sess.channel_session()?;
         channel.exec(&format!(
-            "(sh /home/{user}/psm/scripts/{script_name} >> /tmp/shell_log.log 2>&1 &)"
+            "(sh /home/{user}/psm/scripts/{script_name} {args} >> /tmp/shell_log.log 2>&1 &)"
         ))?;
 
         const CHECK_INTERVAL: u64 = 5;
@@ -156,4 +208,8 @@ pub enum Script {
     StartServer,
     /// backup_save.sh
     BackupSave,
+    /// mount_disk.sh {disk_id}
+    MountDisk(String),
+    /// unmount_disk.sh
+    UnmountDisk,
 }
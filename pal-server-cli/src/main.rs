@@ -1,13 +1,28 @@
+mod cos_storage;
 mod cvm_utils;
+mod data_disk;
+mod dns;
+mod eip;
 mod local_storage;
 mod psm;
+mod security_group;
 mod server_status;
+mod snapshot;
 
-use std::path::Path;
+use std::{path::Path, sync::Arc};
 
 use clap::Parser;
+use cos_storage::{CosStorage, CosStorageConfig};
+use cvm_utils::LaunchConfig;
 use local_storage::LocalSaveStorageConfig;
-use tencent_cloud_sdk::config::ClientConfig;
+use tencent_cloud_sdk::{
+    client::{
+        TencentCloudClient,
+        sts::sts_credential::{AssumeRoleRequest, Policy},
+    },
+    config::ClientConfig,
+    constant::Region,
+};
 
 #[derive(clap::Parser, Debug)]
 struct Args {
@@ -23,9 +38,49 @@ struct Args {
     #[clap(long)]
     save: Option<String>,
 
+    /// max spot instance price per hour for `--new` / `--start`, saved with the server
+    #[clap(long)]
+    max_price: Option<f64>,
+
     #[clap(long)]
     stop: Option<String>,
 
+    /// backup and shut down the instance, without charging if it's pay-as-you-go, `--start` resumes it
+    #[clap(long)]
+    pause: Option<String>,
+
+    /// reinstall the system disk of a running server, keeps the instance and ip
+    #[clap(long)]
+    reinstall: Option<String>,
+
+    /// with `--reinstall`, go on if the backup fails and restore the last save
+    #[clap(long)]
+    force: bool,
+
+    /// with `--new` / `--start`, keep an elastic ip for the server so its address never changes
+    #[clap(long)]
+    eip: bool,
+
+    /// with `--new` / `--start`, keep an A record of this hostname pointed to the server ip
+    #[clap(long)]
+    hostname: Option<String>,
+
+    /// release the elastic ip of a stopped server
+    #[clap(long)]
+    release_eip: Option<String>,
+
+    /// roll the data disk of a stopped server back to its latest snapshot
+    #[clap(long)]
+    rollback: Option<String>,
+
+    /// bake a custom image with the palworld server installed, run again after a game update
+    #[clap(long)]
+    bake_image: bool,
+
+    /// show all servers and their cvm instances
+    #[clap(long)]
+    status: bool,
+
     /// debug mode
     #[clap(long)]
     test: bool,
@@ -34,8 +89,66 @@ struct Args {
 #[derive(Debug, serde::Deserialize)]
 struct Config {
     tcc_config: ClientConfig,
+    #[serde(default)]
+    assume_role: Option<AssumeRoleConfig>,
     server_status_filepath: String,
     local_storage: LocalSaveStorageConfig,
+    #[serde(default)]
+    launch: LaunchConfig,
+    /// share save archives through a bucket
+    #[serde(default)]
+    cos_storage: Option<CosStorageConfig>,
+}
+
+/// `tcc_config` keys only need `sts:AssumeRole`, every other call uses the role's temporary credentials.
+#[derive(Debug, serde::Deserialize)]
+struct AssumeRoleConfig {
+    role_arn: String,
+    #[serde(default = "default_session_name")]
+    session_name: String,
+    /// seconds, refreshed before expiry
+    #[serde(default)]
+    duration: Option<u64>,
+    #[serde(default = "default_sts_region")]
+    region: Region,
+    /// narrows the role's permissions for this session
+    #[serde(default = "default_allowed_actions")]
+    allowed_actions: Vec<String>,
+}
+
+fn default_session_name() -> String {
+    "pal-server-cli".to_owned()
+}
+
+fn default_sts_region() -> Region {
+    Region::Guangzhou
+}
+
+fn default_allowed_actions() -> Vec<String> {
+    vec![
+        "cvm:*".to_owned(),
+        "vpc:*".to_owned(),
+        "cbs:*".to_owned(),
+        "dnspod:*".to_owned(),
+    ]
+}
+
+fn build_client(config: &Config) -> anyhow::Result<TencentCloudClient> {
+    let client = TencentCloudClient::new(&config.tcc_config)?;
+    let Some(assume_role) = &config.assume_role else {
+        return Ok(client);
+    };
+    let mut request = AssumeRoleRequest::new(&assume_role.role_arn, &assume_role.session_name)
+        .policy(&Policy::allow(&assume_role.allowed_actions));
+    if let Some(duration) = assume_role.duration {
+        request = request.duration_seconds(duration);
+    }
+    let provider = client
+        .sts()
+        .credential()
+        .assume_role_provider(&assume_role.region, request);
+    let tcc_config = config.tcc_config.clone().credential_provider(Arc::new(provider));
+    Ok(TencentCloudClient::new(&tcc_config)?)
 }
 
 #[tokio::main]
@@ -49,20 +162,33 @@ async fn main() -> anyhow::Result<()> {
     println!("Config: {:?}", config);
 
     let mut psm = {
-        let client = tencent_cloud_sdk::client::TencentCloudClient::new(&config.tcc_config);
+        let client = build_client(&config)?;
         let server_manager = server_status::ServerManager::new(&config.server_status_filepath)?;
         let local_storage = local_storage::LocalStorage::new(config.local_storage);
-        psm::PalServerManager::new(client, server_manager, local_storage)?
+        let cos_storage = config.cos_storage.as_ref().map(CosStorage::new).transpose()?;
+        psm::PalServerManager::new(client, server_manager, local_storage, config.launch, cos_storage)?
     };
 
     if let Some(name) = args.new {
-        psm.new_save(&name).await?;
+        psm.new_save(&name, args.max_price, args.eip, args.hostname).await?;
     } else if let Some(name) = args.start {
-        psm.restart_save(&name).await?;
+        psm.restart_save(&name, args.max_price, args.eip, args.hostname).await?;
     } else if let Some(name) = args.save {
         psm.save_backup(&name).await?;
     } else if let Some(name) = args.stop {
         psm.stop_server(&name).await?;
+    } else if let Some(name) = args.pause {
+        psm.pause_server(&name).await?;
+    } else if let Some(name) = args.reinstall {
+        psm.reinstall_server(&name, args.force).await?;
+    } else if let Some(name) = args.release_eip {
+        psm.release_eip(&name).await?;
+    } else if let Some(name) = args.rollback {
+        psm.rollback_server(&name).await?;
+    } else if args.bake_image {
+        psm.bake_image().await?;
+    } else if args.status {
+        psm.show_status().await?;
     } else if args.test {
         psm.test().await?;
     }
@@ -1,17 +1,42 @@
-use std::str::FromStr;
+use std::time::Duration;
 
-use tencent_cloud_sdk::{client::TencentCloudClient, constant::Region};
+use futures::TryStreamExt;
+use tencent_cloud_sdk::{
+    client::{
+        TencentCloudClient,
+        cvm::{
+            cvm_image::CreateImageRequest,
+            cvm_instance::{
+                DescribeInstancesRequest, InstanceChargeType, InstanceState, LoginSettings, ResetInstanceRequest,
+                StopChargingMode, StopType,
+            },
+        },
+    },
+    constant::Region,
+    error::ErrorKind,
+};
 
 use crate::{
-    cvm_utils::{query_cvm_ip, query_spot_paid_price},
+    cos_storage::CosStorage,
+    cvm_utils::{
+        LaunchConfig, candidate_regions, query_cvm_ip, query_spot_paid_price, wait_image_ready, wait_instance_state,
+        wait_latest_operation,
+    },
+    data_disk::{attach_save_disk, create_save_disk},
+    dns::point_hostname,
+    eip::{allocate_server_eip, bind_server_eip},
     local_storage::{LocalStorage, Script},
-    server_status::{Server, ServerManager, ServiceInstanceType, Status},
+    security_group::ensure_security_group,
+    server_status::{BakedImage, Server, ServerManager, ServiceInstanceType, Status},
+    snapshot::{SnapshotConfig, create_disk_snapshot, wait_disk_rollback},
 };
 
 pub struct PalServerManager {
     pub client: TencentCloudClient,
     pub server_status: ServerManager,
     pub local_storage: LocalStorage,
+    pub launch: LaunchConfig,
+    pub cos_storage: Option<CosStorage>,
 }
 
 impl PalServerManager {
@@ -19,17 +44,21 @@ impl PalServerManager {
         client: TencentCloudClient,
         server_status: ServerManager,
         local_storage: LocalStorage,
+        launch: LaunchConfig,
+        cos_storage: Option<CosStorage>,
     ) -> anyhow::Result<Self> {
         Ok(Self {
             client,
             server_status,
             local_storage,
+            launch,
+            cos_storage,
         })
     }
 
     pub async fn test(&mut self) -> anyhow::Result<()> {
-        let server = self.server_status.get("test")?;
-        self.restore_save(&server).await?;
+        let mut server = self.server_status.get("test")?;
+        self.restore_save(&mut server).await?;
 
         tokio::time::sleep(std::time::Duration::from_secs(1)).await;
         self.start_server(&server).await?;
@@ -38,32 +67,67 @@ impl PalServerManager {
         Ok(())
     }
 
-    pub async fn new_save(&mut self, name: &str) -> anyhow::Result<()> {
+    /// `eip` reserves an elastic ip for this server, `hostname` is kept pointed to its ip
+    pub async fn new_save(
+        &mut self,
+        name: &str,
+        max_price: Option<f64>,
+        eip: bool,
+        hostname: Option<String>,
+    ) -> anyhow::Result<()> {
         println!("Creating new save: {}", name);
         if self.server_status.get(name).is_ok() {
             anyhow::bail!("Save with name {} already exists", name);
         }
-        // let server = self.q_and_c(name, ServiceInstanceType::T4C16G).await?;
-        let server = self.q_and_c(name, ServiceInstanceType::T2C2G).await?;
+        // let server = self.q_and_c(name, ServiceInstanceType::T4C16G, max_price).await?;
+        let mut server = self
+            .q_and_c(name, ServiceInstanceType::T2C2G, max_price, None, None)
+            .await?;
+        server.hostname = hostname;
         self.server_status.add(&server)?;
+        if eip {
+            self.bind_eip(&mut server).await?;
+        }
+        self.update_hostname(&server).await;
 
         // sleep 10s to wait for instance ready
         println!("Waiting for instance to be ready... sleep 10s");
         tokio::time::sleep(std::time::Duration::from_secs(10)).await;
 
         self.init_server(&server).await?;
-        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-        self.restore_save(&server).await?;
+        if self.mount_save_disk(&mut server).await? {
+            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            self.restore_save(&mut server).await?;
+        }
         tokio::time::sleep(std::time::Duration::from_secs(1)).await;
         self.start_server(&server).await?;
 
         Ok(())
     }
 
-    pub async fn restart_save(&mut self, name: &str) -> anyhow::Result<()> {
+    /// `max_price` replaces the one saved for this server, `eip` reserves an elastic ip for it,
+    /// `hostname` replaces the one saved for this server
+    pub async fn restart_save(
+        &mut self,
+        name: &str,
+        max_price: Option<f64>,
+        eip: bool,
+        hostname: Option<String>,
+    ) -> anyhow::Result<()> {
         println!("Restarting save: {}", name);
         let mut cur_server = self.server_status.get(name)?;
+        if max_price.is_some() {
+            cur_server.max_price = max_price;
+            self.server_status.update(name, &cur_server)?;
+        }
+        if hostname.is_some() {
+            cur_server.hostname = hostname;
+            self.server_status.update(name, &cur_server)?;
+        }
 
+        if cur_server.status == Status::Paused {
+            return self.resume_server(&mut cur_server).await;
+        }
         if let Some(_ip) = &cur_server.ip {
             self.check_status(&mut cur_server).await?;
             if cur_server.status == Status::Running {
@@ -73,20 +137,31 @@ impl PalServerManager {
         }
         let service_instance_type = cur_server.service_instance_type.clone();
 
-        let server = self.q_and_c(name, service_instance_type).await?;
+        let (region, zone) = cur_server.placement();
+        let server = self
+            .q_and_c(name, service_instance_type, cur_server.max_price, region, zone)
+            .await?;
         cur_server.instance_id = server.instance_id;
         cur_server.ip = server.ip;
         cur_server.status = Status::Running;
         cur_server.region = server.region;
+        cur_server.instance_type = server.instance_type;
+        cur_server.image_id = server.image_id;
         self.server_status.update(name, &cur_server)?;
+        if eip || cur_server.eip.is_some() {
+            self.bind_eip(&mut cur_server).await?;
+        }
+        self.update_hostname(&cur_server).await;
 
         // sleep 10s to wait for instance ready
         println!("Waiting for instance to be ready... sleep 10s");
         tokio::time::sleep(std::time::Duration::from_secs(10)).await;
 
         self.init_server(&cur_server).await?;
-        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-        self.restore_save(&cur_server).await?;
+        if self.mount_save_disk(&mut cur_server).await? {
+            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            self.restore_save(&mut cur_server).await?;
+        }
         tokio::time::sleep(std::time::Duration::from_secs(1)).await;
         self.start_server(&cur_server).await?;
 
@@ -110,33 +185,505 @@ impl PalServerManager {
         println!("Stopping server: {}", name);
         let mut server = self.server_status.get(name)?;
 
-        if server.status != Status::Running {
-            anyhow::bail!("Server {} is not running", name);
+        match server.status {
+            // the save stays on the disk
+            Status::Running if server.disk.is_some() => self.unmount_save_disk(&server).await?,
+            Status::Running => {
+                if let Err(e) = self.backup_save(&mut server).await {
+                    // the live world is only on this instance, it is kept with a cloud-side copy of its system disk
+                    if let Some(config) = self.launch.snapshot.clone() {
+                        self.snapshot_server(&mut server, &config).await?;
+                        anyhow::bail!(
+                            "Backup save failed: {}, instance kept with snapshot {:?} of its system disk, \
+                             run --stop again once the save can be downloaded",
+                            e,
+                            server.snapshot.last().map(|s| &s.snapshot_id)
+                        );
+                    }
+                    return Err(e);
+                }
+            }
+            // saved when paused
+            Status::Paused => {}
+            _ => anyhow::bail!("Server {} is not running", name),
         }
-        self.backup_save(&mut server).await?;
-        let region = Region::from_str(&server.region.as_ref().unwrap()).unwrap();
-        let instance_id = server.instance_id.as_ref().unwrap();
+        if let Some(config) = self.launch.snapshot.clone() {
+            self.snapshot_server(&mut server, &config).await?;
+        }
+        let (region, instance_id) = server.instance()?;
         self.client
             .cvm()
             .instances()
-            .terminate_instance(&region, instance_id)
+            .terminate_instance(&region, &instance_id)
             .await?;
         server.status = Status::Stopped;
         server.ip = None;
         server.instance_id = None;
         self.server_status.update(&server.name, &server)?;
+        self.update_hostname(&server).await;
 
         Ok(())
     }
 
-    // easy for test
-    async fn q_and_c(&self, name: &str, service_instance_type: ServiceInstanceType) -> anyhow::Result<Server> {
-        self.query_and_create(
-            name,
-            &[Region::Nanjing, Region::Shanghai, Region::Guangzhou],
-            &service_instance_type,
+    /// release the reserved eip of a stopped server, its next instances get a normal public ip again
+    pub async fn release_eip(&mut self, name: &str) -> anyhow::Result<()> {
+        let mut server = self.server_status.get(name)?;
+        let Some(eip) = server.eip.clone() else {
+            anyhow::bail!("Server {} has no eip", name);
+        };
+        if server.status != Status::Stopped {
+            anyhow::bail!("Server {} is not stopped, its instance would lose the public ip", name);
+        }
+        self.client
+            .cvm()
+            .addresses()
+            .release_addresses(&eip.region, vec![eip.address_id.clone()])
+            .await?;
+        server.eip = None;
+        self.server_status.update(name, &server)?;
+        println!(
+            "Released eip {} ({}) of server {}",
+            eip.address_ip, eip.address_id, name
+        );
+        Ok(())
+    }
+
+    /// roll the data disk of a stopped server back to its latest snapshot, the save of the next start is lost then
+    pub async fn rollback_server(&mut self, name: &str) -> anyhow::Result<()> {
+        let server = self.server_status.get(name)?;
+        if server.status != Status::Stopped {
+            anyhow::bail!("Server {} is not stopped", name);
+        }
+        let Some(disk) = &server.disk else {
+            anyhow::bail!(
+                "Server {} has no data disk, create a disk from its latest snapshot {:?} to recover the save",
+                name,
+                server.snapshot.last().map(|s| &s.snapshot_id)
+            );
+        };
+        let snapshot = server
+            .snapshot
+            .iter()
+            .rev()
+            .find(|s| s.disk_id == disk.disk_id)
+            .ok_or_else(|| anyhow::anyhow!("No snapshot of data disk {} found", disk.disk_id))?;
+        println!(
+            "Rolling back data disk {} of server {} to snapshot {}",
+            disk.disk_id, name, snapshot.snapshot_id
+        );
+        self.client
+            .cbs()
+            .snapshots()
+            .apply_snapshot(&disk.region, &snapshot.snapshot_id, &disk.disk_id)
+            .await?;
+        wait_disk_rollback(&self.client, &disk.region, &disk.disk_id, Duration::from_secs(1800)).await?;
+        println!("Rollback done");
+        Ok(())
+    }
+
+    /// backup save and shut the instance down, the disk with the installed server is kept.
+    /// only pay-as-you-go instances stop charging, spot ones are still charged while paused.
+    pub async fn pause_server(&mut self, name: &str) -> anyhow::Result<()> {
+        println!("Pausing server: {}", name);
+        let mut server = self.server_status.get(name)?;
+
+        if server.status != Status::Running {
+            anyhow::bail!("Server {} is not running", name);
+        }
+        let (region, instance_id) = server.instance()?;
+        let instance = self
+            .client
+            .cvm()
+            .instances()
+            .describe_instance(&region, &instance_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("cvm {} not found in {}", instance_id, region))?;
+        // STOP_CHARGING is only for POSTPAID_BY_HOUR
+        let stop_charging_mode = match instance.instance_charge_type {
+            InstanceChargeType::POSTPAID_BY_HOUR => StopChargingMode::StopCharging,
+            charge_type => {
+                println!(
+                    "Instance {} is {}, it is still charged while paused, use --stop to stop charging",
+                    instance_id, charge_type
+                );
+                StopChargingMode::KeepCharging
+            }
+        };
+        match server.disk {
+            Some(_) => self.unmount_save_disk(&server).await?,
+            None => self.backup_save(&mut server).await?,
+        }
+        self.client
+            .cvm()
+            .instances()
+            .stop_instance(
+                &region,
+                &instance_id,
+                Some(StopType::SoftFirst),
+                Some(stop_charging_mode),
+            )
+            .await?;
+        wait_instance_state(
+            &self.client,
+            &region,
+            &instance_id,
+            InstanceState::STOPPED,
+            Duration::from_secs(180),
         )
-        .await
+        .await?;
+        // the public ip is released with STOP_CHARGING only, it is queried again on resume anyway
+        let released = stop_charging_mode == StopChargingMode::StopCharging;
+        server.status = Status::Paused;
+        if released {
+            server.ip = None;
+        }
+        self.server_status.update(&server.name, &server)?;
+        if released {
+            self.update_hostname(&server).await;
+        }
+        println!("Server {} paused, instance: {}", name, instance_id);
+
+        Ok(())
+    }
+
+    /// start the paused instance again, only the game server needs to be started
+    async fn resume_server(&mut self, server: &mut Server) -> anyhow::Result<()> {
+        let (region, instance_id) = server.instance()?;
+        println!("Resuming server: {}, instance: {}", server.name, instance_id);
+        match self
+            .client
+            .cvm()
+            .instances()
+            .start_instance(&region, &instance_id)
+            .await
+        {
+            Ok(()) => {}
+            // may be sold out in this zone since the resources were released
+            Err(e) if e.kind() == ErrorKind::ResourceSoldOut => {
+                anyhow::bail!(
+                    "Failed to start {}: {e}, stop it with --stop and start again to create a new one",
+                    instance_id
+                );
+            }
+            Err(e) => return Err(e.into()),
+        }
+        let ip = query_cvm_ip(&self.client, &region, &instance_id).await?;
+        server.ip = Some(ip);
+        server.status = Status::Running;
+        self.server_status.update(&server.name, server)?;
+        self.update_hostname(server).await;
+
+        // wait for sshd
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        // still attached, but not mounted after boot
+        if server.disk.is_some() {
+            self.mount_save_disk(server).await?;
+        }
+        self.start_server(server).await?;
+        Ok(())
+    }
+
+    /// reinstall the system disk of a running server with the same image, then install and restore the save again.
+    /// the instance keeps its id, ip and zone.
+    /// `force` goes on if the backup fails, everything since the last save is lost then
+    pub async fn reinstall_server(&mut self, name: &str, force: bool) -> anyhow::Result<()> {
+        println!("Reinstalling server: {}", name);
+        let mut server = self.server_status.get(name)?;
+
+        if server.status != Status::Running {
+            anyhow::bail!("Server {} is not running", name);
+        }
+        if server.disk.is_some() {
+            // the data disk is kept by the reset
+            self.unmount_save_disk(&server).await?;
+        } else if let Err(e) = self.backup_save(&mut server).await {
+            // the reset wipes the live world on the system disk
+            if !force {
+                anyhow::bail!(
+                    "Backup save failed: {}, run with --force to reinstall anyway and restore the last save {:?}",
+                    e,
+                    server.save
+                );
+            }
+            println!("Backup save failed: {}, restore the last save {:?}", e, server.save);
+        }
+        let (region, instance_id) = server.instance()?;
+        let request =
+            ResetInstanceRequest::new(&instance_id).login_settings(LoginSettings::key_ids(self.key_ids().await?));
+        let request_id = self.client.cvm().instances().reset_instance(&region, &request).await?;
+        wait_latest_operation(
+            &self.client,
+            &region,
+            &instance_id,
+            &request_id,
+            Duration::from_secs(600),
+        )
+        .await?;
+        server.ip = Some(query_cvm_ip(&self.client, &region, &instance_id).await?);
+        self.server_status.update(&server.name, &server)?;
+
+        // wait for sshd
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        self.init_server(&server).await?;
+        if self.mount_save_disk(&mut server).await? {
+            self.restore_save(&mut server).await?;
+        }
+        self.start_server(&server).await?;
+        Ok(())
+    }
+
+    /// launch a builder instance, install the palworld server on it and save it as an image
+    /// copied to every candidate region. run it again after a game update to replace the images.
+    pub async fn bake_image(&mut self) -> anyhow::Result<()> {
+        let regions = candidate_regions(&self.client, &self.launch.regions).await?;
+        println!("Baking image for regions: {:?}", regions);
+        // always from the public image
+        let builder = self
+            .query_and_create("psm-bake", &regions, None, &ServiceInstanceType::T2C2G, None, &[])
+            .await?;
+        let (region, instance_id) = builder.instance()?;
+
+        let baked = self.bake_from(&builder, &regions).await;
+        println!("Terminating builder instance {}", instance_id);
+        // the images are paid for already, record them before reporting a failed termination
+        let terminated = self
+            .client
+            .cvm()
+            .instances()
+            .terminate_instance(&region, &instance_id)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to terminate builder instance {}: {}", instance_id, e));
+        let images = match baked {
+            Ok(images) => images,
+            Err(e) => {
+                if let Err(terminate_error) = terminated {
+                    println!("{}", terminate_error);
+                }
+                return Err(e);
+            }
+        };
+
+        let mut retired = self.server_status.retired_images();
+        retired.extend(
+            self.server_status
+                .images()
+                .into_iter()
+                .filter(|old| !images.iter().any(|i| i.image_id == old.image_id)),
+        );
+        self.server_status.set_images(images.clone())?;
+        println!("Baked images: {:?}", images);
+        self.delete_retired_images(retired).await?;
+        terminated
+    }
+
+    /// delete replaced baked images, those still used by an instance are kept for `--reinstall`
+    async fn delete_retired_images(&mut self, retired: Vec<BakedImage>) -> anyhow::Result<()> {
+        let servers = self.server_status.list();
+        let mut kept = vec![];
+        for old in retired {
+            if let Some(server) = servers
+                .iter()
+                .find(|s| s.instance_id.is_some() && s.image_id.as_ref() == Some(&old.image_id))
+            {
+                println!(
+                    "Keep old image {} in {}, server {} is launched from it",
+                    old.image_id, old.region, server.name
+                );
+                kept.push(old);
+                continue;
+            }
+            if let Err(e) = self
+                .client
+                .cvm()
+                .images()
+                .delete_images(&old.region, vec![old.image_id.clone()], true)
+                .await
+            {
+                println!("Failed to delete old image {} in {}: {}", old.image_id, old.region, e);
+                kept.push(old);
+            }
+        }
+        self.server_status.set_retired_images(kept)
+    }
+
+    async fn bake_from(&self, builder: &Server, regions: &[Region]) -> anyhow::Result<Vec<BakedImage>> {
+        let (region, instance_id) = builder.instance()?;
+        println!("Waiting for instance to be ready... sleep 10s");
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        self.init_server(builder).await?;
+
+        let image_name = format!(
+            "psm-palworld-{}",
+            std::time::UNIX_EPOCH.elapsed().unwrap_or_default().as_secs()
+        );
+        // the running instance is shut down for a consistent disk, it is terminated afterwards anyway
+        let request = CreateImageRequest::new(&image_name, &instance_id)
+            .force_poweroff(true)
+            .tag("app", "palworld");
+        let image_id = self.client.cvm().images().create_image(&region, &request).await?;
+        println!("Creating image {} ({}) in {}", image_name, image_id, region);
+        match self.sync_baked_image(&region, &image_id, regions).await {
+            Ok(images) => Ok(images),
+            Err(e) => {
+                if let Err(delete_error) = self
+                    .client
+                    .cvm()
+                    .images()
+                    .delete_images(&region, vec![image_id.clone()], true)
+                    .await
+                {
+                    println!("Failed to delete image {} in {}: {}", image_id, region, delete_error);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// wait for the created image and copy it to the other `regions`
+    async fn sync_baked_image(
+        &self,
+        region: &Region,
+        image_id: &str,
+        regions: &[Region],
+    ) -> anyhow::Result<Vec<BakedImage>> {
+        wait_image_ready(&self.client, region, image_id, Duration::from_secs(1800)).await?;
+
+        let mut images = vec![BakedImage {
+            region: region.clone(),
+            image_id: image_id.to_owned(),
+        }];
+        let destinations: Vec<Region> = regions.iter().filter(|r| *r != region).cloned().collect();
+        if !destinations.is_empty() {
+            // usable once SYNCING is done, the public image is used until then
+            let synced = self
+                .client
+                .cvm()
+                .images()
+                .sync_images(region, image_id, destinations)
+                .await?;
+            println!("Syncing image {} to {:?}", image_id, synced);
+            images.extend(synced.into_iter().map(|i| BakedImage {
+                region: i.region,
+                image_id: i.image_id,
+            }));
+        }
+        Ok(images)
+    }
+
+    /// print every server with its cvm instance, servers whose instance is gone are marked as stopped
+    pub async fn show_status(&mut self) -> anyhow::Result<()> {
+        for mut server in self.server_status.list() {
+            let Ok((region, instance_id)) = server.instance() else {
+                println!("{}: {:?}, save: {:?}", server.name, server.status, server.save);
+                continue;
+            };
+            match self
+                .client
+                .cvm()
+                .instances()
+                .describe_instance(&region, &instance_id)
+                .await
+            {
+                Ok(Some(instance)) => {
+                    println!(
+                        "{}: {:?}, save: {:?}\n  {} ({}) {} in {}, {} {}C{}G, ip: {}, created: {}, latest operation: {} {}",
+                        server.name,
+                        server.status,
+                        server.save,
+                        instance.instance_id,
+                        instance.instance_name,
+                        instance.instance_state,
+                        instance.placement.zone,
+                        instance.instance_type,
+                        instance.cpu,
+                        instance.memory,
+                        instance.public_ip().unwrap_or("-"),
+                        instance.created_time.map(|t| t.to_rfc3339()).unwrap_or_default(),
+                        instance.latest_operation.as_deref().unwrap_or("-"),
+                        instance
+                            .latest_operation_state
+                            .as_ref()
+                            .map(|s| s.to_string())
+                            .unwrap_or_default(),
+                    );
+                    if server.ip.as_deref() != instance.public_ip() {
+                        server.ip = instance.public_ip().map(ToOwned::to_owned);
+                        self.server_status.update(&server.name, &server)?;
+                    }
+                }
+                // keep listing the other servers
+                Err(e) => {
+                    println!(
+                        "{}: {:?}, save: {:?}\n  failed to describe instance {} in {}: {}",
+                        server.name, server.status, server.save, instance_id, region, e
+                    );
+                }
+                Ok(None) => {
+                    println!(
+                        "{}: instance {} not found in {}, mark as stopped",
+                        server.name, instance_id, region
+                    );
+                    server.status = Status::Stopped;
+                    server.ip = None;
+                    server.instance_id = None;
+                    self.server_status.update(&server.name, &server)?;
+                }
+            }
+        }
+
+        // left behind by an interrupted command and still charged
+        let tracked = self
+            .server_status
+            .list()
+            .into_iter()
+            .filter_map(|s| s.instance_id)
+            .collect::<Vec<_>>();
+        for region in &self.launch.regions {
+            let request = DescribeInstancesRequest::new().tag("app", "palworld");
+            let untracked = self
+                .client
+                .cvm()
+                .instances()
+                .describe_instances_stream(region, request)
+                .try_filter(|i| std::future::ready(!tracked.contains(&i.instance_id)))
+                .try_collect::<Vec<_>>()
+                .await;
+            match untracked {
+                Ok(instances) => {
+                    for instance in instances {
+                        println!(
+                            "untracked instance {} ({}) {} in {}, server: {}",
+                            instance.instance_id,
+                            instance.instance_name,
+                            instance.instance_state,
+                            instance.placement.zone,
+                            instance.tag("psm-server").unwrap_or("-"),
+                        );
+                    }
+                }
+                Err(e) => println!("failed to list instances in {region}: {e}"),
+            }
+        }
+        Ok(())
+    }
+
+    // easy for test
+    async fn q_and_c(
+        &self,
+        name: &str,
+        service_instance_type: ServiceInstanceType,
+        max_price: Option<f64>,
+        region: Option<&Region>,
+        zone: Option<&str>,
+    ) -> anyhow::Result<Server> {
+        let images = self.server_status.images();
+        // the data disk and the eip can only be bound in their own zone / region
+        let regions = match region {
+            Some(region) => vec![region.clone()],
+            None => candidate_regions(&self.client, &self.launch.regions).await?,
+        };
+        self.query_and_create(name, &regions, zone, &service_instance_type, max_price, &images)
+            .await
         // self.query_and_create(name, &[Region::Nanjing], &ServiceInstanceType::T2C2G)
         //     .await
     }
@@ -146,64 +693,95 @@ impl PalServerManager {
         &self,
         name: &str,
         region: &[Region],
+        zone: Option<&str>,
         service_instance_type: &ServiceInstanceType,
+        max_price: Option<f64>,
+        images: &[BakedImage],
     ) -> anyhow::Result<Server> {
-        let prices = query_spot_paid_price(&self.client, region, service_instance_type).await?;
+        let mut prices = query_spot_paid_price(
+            &self.client,
+            region,
+            service_instance_type,
+            &self.launch,
+            name,
+            max_price,
+            images,
+        )
+        .await?;
+        if let Some(zone) = zone {
+            prices.retain(|(_, (_, request))| request.placement.zone == zone);
+        }
+        if prices.is_empty() {
+            anyhow::bail!(
+                "No zone available for {service_instance_type:?} under max price {max_price:?}{}",
+                zone.map(|zone| format!(" in {zone}")).unwrap_or_default()
+            );
+        }
         // println!(
         //     "[1] Cheapest spot price info: {:?}",
         //     (price, &region, &zone, &instance_type)
         // );
 
-        let key_ids = self
-            .client
-            .cvm()
-            .keys()
-            .describe_key_pairs(&Region::Hongkong) // whatever here
-            .await
-            .map(|vk| vk.into_iter().map(|k| k.key_id).collect::<Vec<_>>())?;
+        let key_ids = self.key_ids().await?;
+        let mut security_groups = vec![];
+        for region in region {
+            let id = ensure_security_group(&self.client, region, &self.launch.security_group).await?;
+            security_groups.push((region.clone(), id));
+        }
 
         let mut final_service_id = None;
         let mut final_region = None;
+        let mut final_instance_type = None;
+        let mut final_image_id = None;
 
-        for (price, (region, zone, instance_type)) in prices {
+        for (price, (region, request)) in prices {
+            let (zone, instance_type) = (request.placement.zone.clone(), request.instance_type.clone());
             println!(
                 "[1] Trying to create instance at region: {}, zone: {}, type: {}, price: {:?}",
                 region, zone, instance_type, price
             );
-            let security_group_id = self
-                .client
-                .cvm()
-                .security_group()
-                .describe_security_groups(&region)
-                .await?
-                .into_iter()
-                .filter_map(|sg| {
-                    sg.security_group_name
-                        .to_ascii_lowercase()
-                        .contains("palworld")
-                        .then_some(sg.security_group_id)
-                })
-                .collect::<Vec<_>>();
-
-            if let Ok(server_id) = self
-                .client
-                .cvm()
-                .instances()
-                .run_instance(&region, &zone, &instance_type, &key_ids, security_group_id)
-                .await
-            {
-                println!(
-                    "[1] Successfully created instance at region: {}, zone: {}, type: {}, price: {:?}, id: {}",
-                    region, zone, instance_type, price, server_id
-                );
-                final_service_id = Some(server_id);
-                final_region = Some(region);
-                break;
-            } else {
-                println!(
-                    "[1] Failed to create instance at region: {}, zone: {}, type: {}, price: {:?}",
-                    region, zone, instance_type, price
-                );
+            let security_group_id = security_groups
+                .iter()
+                .find(|(r, _)| *r == region)
+                .map(|(_, id)| id.clone())
+                .ok_or_else(|| anyhow::anyhow!("no security group in {region}"))?;
+
+            // the same request the price was quoted for
+            let request = request
+                .key_ids(&key_ids)
+                .security_group_ids(vec![security_group_id])
+                .client_token(format!(
+                    "psm-{}-{}",
+                    zone,
+                    std::time::UNIX_EPOCH.elapsed().unwrap_or_default().as_millis()
+                ));
+            match self.client.cvm().instances().run_instance(&region, &request).await {
+                Ok(server_id) => {
+                    println!(
+                        "[1] Successfully created instance at region: {}, zone: {}, type: {}, price: {:?}, id: {}",
+                        region, zone, instance_type, price, server_id
+                    );
+                    final_service_id = Some(server_id);
+                    final_region = Some(region);
+                    final_instance_type = Some(instance_type);
+                    final_image_id = request.image_id;
+                    break;
+                }
+                // no point trying other zones with bad credentials
+                Err(e) if e.kind() == ErrorKind::Auth => {
+                    anyhow::bail!("Failed to create instance: {e}");
+                }
+                Err(e) => {
+                    println!(
+                        "[1] Failed to create instance at region: {}, zone: {}, type: {}, price: {:?}, {:?}: {}",
+                        region,
+                        zone,
+                        instance_type,
+                        price,
+                        e.kind(),
+                        e
+                    );
+                }
             }
         }
         let server_id = final_service_id.ok_or(anyhow::anyhow!("Failed to create instance"))?;
@@ -215,39 +793,218 @@ impl PalServerManager {
             name: name.to_string(),
             status: Status::Running,
             service_instance_type: service_instance_type.clone(),
-            // instance_type,
+            instance_type: final_instance_type,
             save: None,
             ip: Some(ip),
-            region: Some(region.to_string()),
+            region: Some(region),
             instance_id: Some(server_id),
+            max_price,
+            image_id: final_image_id,
+            disk: None,
+            snapshot: vec![],
+            eip: None,
+            hostname: None,
         })
     }
 
+    // step 1.5 bind the reserved eip of the server to the new instance, allocated on first use
+    async fn bind_eip(&mut self, server: &mut Server) -> anyhow::Result<()> {
+        let (region, instance_id) = server.instance()?;
+        let eip = match &server.eip {
+            Some(eip) => eip.clone(),
+            None => {
+                let eip = allocate_server_eip(&self.client, &server.name, &region).await?;
+                server.eip = Some(eip.clone());
+                self.server_status.update(&server.name, server)?;
+                eip
+            }
+        };
+        if eip.region != region {
+            anyhow::bail!(
+                "eip {} is in {}, instance {} in {}",
+                eip.address_id,
+                eip.region,
+                instance_id,
+                region
+            );
+        }
+        bind_server_eip(&self.client, &eip, &instance_id).await?;
+        server.ip = Some(eip.address_ip.clone());
+        self.server_status.update(&server.name, server)?;
+        println!("[1] Bound eip {} to instance {}", eip.address_ip, instance_id);
+        Ok(())
+    }
+
+    // step 1.6 point the hostname to the server ip, or to the placeholder without an ip.
+    // players can still join by ip, so a failure is only reported
+    async fn update_hostname(&self, server: &Server) {
+        let Some(hostname) = &server.hostname else {
+            return;
+        };
+        let dns = &self.launch.dns;
+        let ip = server.ip.as_deref().unwrap_or(&dns.placeholder);
+        match point_hostname(&self.client, hostname, ip, dns).await {
+            Ok(()) => println!("[1] Pointed {} to {}", hostname, ip),
+            Err(e) => println!("[1] Failed to point {} to {}: {}", hostname, ip, e),
+        }
+    }
+
     // step 2 init server install necessaries
     async fn init_server(&self, server: &Server) -> anyhow::Result<()> {
         let ip = server.ip.as_ref().expect("No IP found for server");
         println!("[2] Start Initializing server: {} , ip: {}", server.name, ip);
         self.local_storage.upload_scripts(ip).await?;
 
+        let baked = self.server_status.images();
+        if let Some(image_id) = &server.image_id
+            && baked.iter().any(|i| &i.image_id == image_id)
+        {
+            println!("[2] Server installed in baked image {}, skip install", image_id);
+            return Ok(());
+        }
+
         let res = self.local_storage.exec_shell(ip, Script::InstallServer).await?;
         println!("[2] Init server done, logs: {}", res);
         Ok(())
     }
 
-    // step 3 restore save
-    async fn restore_save(&self, server: &Server) -> anyhow::Result<()> {
+    // step 2.5 attach and mount the data disk, a new one in disk storage mode.
+    // return whether the save still has to be restored from the archive
+    async fn mount_save_disk(&mut self, server: &mut Server) -> anyhow::Result<bool> {
+        let (region, instance_id) = server.instance()?;
+        let (disk, restore) = match (&server.disk, &self.launch.data_disk) {
+            (Some(disk), _) => (disk.clone(), false),
+            (None, Some(config)) => {
+                let instance = self
+                    .client
+                    .cvm()
+                    .instances()
+                    .describe_instance(&region, &instance_id)
+                    .await?
+                    .ok_or_else(|| anyhow::anyhow!("cvm {instance_id} not found in {region}"))?;
+                let disk =
+                    create_save_disk(&self.client, &server.name, &region, &instance.placement.zone, config).await?;
+                server.disk = Some(disk.clone());
+                self.server_status.update(&server.name, server)?;
+                // the last archive, if any, is moved to the new disk
+                (disk, true)
+            }
+            (None, None) => return Ok(true),
+        };
+        let ip = server.ip.as_ref().expect("No IP found for server");
+        println!(
+            "[2] Mounting data disk {} on server: {} , ip: {}",
+            disk.disk_id, server.name, ip
+        );
+        attach_save_disk(&self.client, &disk, &instance_id).await?;
+        // the exit code is not seen, the game must not start on the system disk
+        let log = self
+            .local_storage
+            .exec_shell(ip, Script::MountDisk(disk.disk_id.clone()))
+            .await?;
+        if log.trim() != "Disk mounted" {
+            anyhow::bail!("Mount data disk {} failed: {}", disk.disk_id, log.trim());
+        }
+        Ok(restore)
+    }
+
+    /// snapshot the data disk, or the system disk of the current instance, and delete the ones beyond `keep`
+    async fn snapshot_server(&mut self, server: &mut Server, config: &SnapshotConfig) -> anyhow::Result<()> {
+        let (region, disk_id) = match &server.disk {
+            Some(disk) => (disk.region.clone(), disk.disk_id.clone()),
+            None => {
+                let (region, instance_id) = server.instance()?;
+                let instance = self
+                    .client
+                    .cvm()
+                    .instances()
+                    .describe_instance(&region, &instance_id)
+                    .await?
+                    .ok_or_else(|| anyhow::anyhow!("cvm {instance_id} not found in {region}"))?;
+                let disk_id = instance
+                    .system_disk
+                    .disk_id
+                    .ok_or_else(|| anyhow::anyhow!("cvm {instance_id} has no system disk id"))?;
+                (region, disk_id)
+            }
+        };
+        let snapshot = create_disk_snapshot(&self.client, &server.name, &region, &disk_id).await?;
+        println!("Snapshot {} of server {} done", snapshot.snapshot_id, server.name);
+        server.snapshot.push(snapshot);
+
+        let expired = server.snapshot.len().saturating_sub(config.keep.max(1));
+        let mut kept = vec![];
+        for (i, old) in std::mem::take(&mut server.snapshot).into_iter().enumerate() {
+            if i >= expired {
+                kept.push(old);
+                continue;
+            }
+            match self
+                .client
+                .cbs()
+                .snapshots()
+                .delete_snapshots(&old.region, vec![old.snapshot_id.clone()])
+                .await
+            {
+                Ok(()) => println!("Deleted old snapshot {} in {}", old.snapshot_id, old.region),
+                // tried again next time
+                Err(e) => {
+                    println!(
+                        "Failed to delete old snapshot {} in {}: {}",
+                        old.snapshot_id, old.region, e
+                    );
+                    kept.push(old);
+                }
+            }
+        }
+        server.snapshot = kept;
+        self.server_status.update(&server.name, server)?;
+        Ok(())
+    }
+
+    /// stop the game and flush the data disk before the instance is stopped or gone
+    async fn unmount_save_disk(&self, server: &Server) -> anyhow::Result<()> {
         let ip = server.ip.as_ref().expect("No IP found for server");
-        let Some(save_name) = &server.save else {
+        println!("Unmounting data disk of server: {} , ip: {}", server.name, ip);
+        // the instance is terminated or reset next, only once the world is flushed to the disk
+        let log = self.local_storage.exec_shell(ip, Script::UnmountDisk).await?;
+        if log.trim() != "Disk unmounted" {
+            anyhow::bail!("Unmount data disk of server {} failed: {}", server.name, log.trim());
+        }
+        Ok(())
+    }
+
+    // step 3 restore save
+    /// the newest archive of this machine and cos, e.g. stopped by another team member
+    async fn restore_save(&mut self, server: &mut Server) -> anyhow::Result<()> {
+        let ip = server.ip.clone().expect("No IP found for server");
+        let latest = match &self.cos_storage {
+            Some(cos) => cos.latest_save(&server.name).await?,
+            None => None,
+        };
+        // archives are named by time
+        let Some(save_name) = latest.into_iter().chain(server.save.clone()).max() else {
             // anyhow::bail!("No save found for server {}", server.name);
             println!("[3] No save found for server {}, skip restore save", server.name);
             return Ok(());
         };
+        if let Some(cos) = &self.cos_storage
+            && !self.local_storage.has_save(&save_name).await?
+        {
+            println!("[3] Downloading save: {} from cos", save_name);
+            let content = cos.download_save(&server.name, &save_name).await?;
+            self.local_storage.write_save(&save_name, content).await?;
+        }
+        if server.save.as_ref() != Some(&save_name) {
+            server.save = Some(save_name.clone());
+            self.server_status.update(&server.name, server)?;
+        }
         println!(
             "[3] Start Restoring save: {} to server: {} , ip: {}",
             save_name, server.name, ip
         );
-        self.local_storage.upload_saves(save_name, ip).await?;
-        self.local_storage.exec_shell(ip, Script::RestoreSave).await?;
+        self.local_storage.upload_saves(&save_name, &ip).await?;
+        self.local_storage.exec_shell(&ip, Script::RestoreSave).await?;
         println!("[3] Restore save done");
         Ok(())
     }
@@ -265,8 +1022,18 @@ impl PalServerManager {
     async fn backup_save(&mut self, server: &mut Server) -> anyhow::Result<()> {
         let ip = server.ip.as_ref().expect("No IP found for server");
         println!("[5] Start backing up save from server: {} , ip: {}", server.name, ip);
-        let save_name = self.local_storage.exec_shell(ip, Script::BackupSave).await?;
+        let save_name = self
+            .local_storage
+            .exec_shell(ip, Script::BackupSave)
+            .await?
+            .trim()
+            .to_owned();
         self.local_storage.download_saves(&save_name, ip).await?;
+        if let Some(cos) = &self.cos_storage {
+            let content = self.local_storage.read_save(&save_name).await?;
+            cos.upload_save(&server.name, &save_name, content).await?;
+            println!("[5] Uploaded save: {} to cos", save_name);
+        }
         server.save = Some(save_name);
         self.server_status.update(&server.name, server)?;
         println!("[5] Backup save done");
@@ -274,6 +1041,37 @@ impl PalServerManager {
     }
 
     // helper functions ...
+    /// only the key pair of `ssh.prikey`, imported on first use, key pairs are not regional
+    async fn key_ids(&self) -> anyhow::Result<Vec<String>> {
+        let ssh = self.local_storage.ssh();
+        let public_key = ssh.public_key()?;
+        let keys = self.client.cvm().keys();
+        let region = Region::Hongkong; // whatever here
+        if let Some(key) = keys
+            .describe_key_pairs(&region)
+            .await?
+            .into_iter()
+            .find(|k| k.matches(&public_key))
+        {
+            return Ok(vec![key.key_id]);
+        }
+        // DescribeKeyPairs above only lists the first page
+        if let Some(key) = keys.find_key_pair(&region, &ssh.key_name).await? {
+            if key.matches(&public_key) {
+                return Ok(vec![key.key_id]);
+            }
+            anyhow::bail!(
+                "key pair {} ({}) is not the public key of {}, rename or delete it",
+                ssh.key_name,
+                key.key_id,
+                ssh.prikey
+            );
+        }
+        let key_id = keys.import_key_pair(&region, &ssh.key_name, &public_key).await?;
+        println!("Imported {} as key pair {} ({})", ssh.prikey, ssh.key_name, key_id);
+        Ok(vec![key_id])
+    }
+
     async fn check_status(&mut self, server: &mut Server) -> anyhow::Result<()> {
         let ip = server.ip.as_ref().expect("No IP found for server");
         if let Ok(true) = self.local_storage.get_heartbeat(ip).await {
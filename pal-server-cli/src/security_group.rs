@@ -0,0 +1,257 @@
+use serde::Deserialize;
+use tencent_cloud_sdk::{
+    client::{
+        TencentCloudClient,
+        cvm::cvm_security_group::{
+            CreateSecurityGroupRequest, PolicyAction, Protocol, SecurityGroupPolicy, SecurityGroupPolicySet,
+        },
+    },
+    constant::Region,
+};
+
+/// the security group of every server, `[launch.security_group]` in config file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SecurityGroupConfig {
+    /// created in a region without a group of this name
+    pub name: String,
+    /// UDP, open to anyone
+    pub game_port: u16,
+    /// UDP steam query port, open to anyone
+    pub query_port: u16,
+    /// TCP, used by this cli to set up the server
+    pub ssh_port: u16,
+    pub ssh_cidrs: Vec<String>,
+    /// TCP REST API, only open to `admin_cidrs`
+    pub rest_port: Option<u16>,
+    /// TCP RCON, only open to `admin_cidrs`
+    pub rcon_port: Option<u16>,
+    pub admin_cidrs: Vec<String>,
+}
+
+impl Default for SecurityGroupConfig {
+    fn default() -> Self {
+        Self {
+            name: "palworld".to_owned(),
+            game_port: 8211,
+            query_port: 27015,
+            ssh_port: 22,
+            ssh_cidrs: vec!["0.0.0.0/0".to_owned()],
+            rest_port: None,
+            rcon_port: None,
+            admin_cidrs: vec![],
+        }
+    }
+}
+
+impl SecurityGroupConfig {
+    /// ingress rules every server needs
+    fn ingress(&self) -> Vec<SecurityGroupPolicy> {
+        let mut rules = vec![
+            SecurityGroupPolicy::accept(Protocol::Udp, self.game_port, "0.0.0.0/0").description("palworld game"),
+            SecurityGroupPolicy::accept(Protocol::Udp, self.query_port, "0.0.0.0/0").description("palworld query"),
+        ];
+        for cidr in &self.ssh_cidrs {
+            rules.push(SecurityGroupPolicy::accept(Protocol::Tcp, self.ssh_port, cidr).description("psm ssh"));
+        }
+        for (port, description) in self.admin_ports() {
+            for cidr in &self.admin_cidrs {
+                rules.push(SecurityGroupPolicy::accept(Protocol::Tcp, port, cidr).description(description));
+            }
+        }
+        rules
+    }
+
+    fn admin_ports(&self) -> Vec<(u16, &'static str)> {
+        [(self.rest_port, "palworld rest"), (self.rcon_port, "palworld rcon")]
+            .into_iter()
+            .filter_map(|(port, description)| port.map(|port| (port, description)))
+            .collect()
+    }
+
+    /// accepted rules covering an admin port from outside `admin_cidrs`
+    fn is_exposed(&self, rule: &SecurityGroupPolicy) -> bool {
+        let admin_port = self.admin_ports().iter().any(|(port, _)| covers_tcp_port(rule, *port));
+        admin_port
+            && rule.action == Some(PolicyAction::ACCEPT)
+            && !rule
+                .cidr_block
+                .as_ref()
+                .is_some_and(|cidr| self.admin_cidrs.contains(cidr))
+    }
+}
+
+/// whether `rule` applies to TCP `port`, its port is `ALL`, `8211`, `8000-8100` or `80,443`.
+/// a missing protocol or port means all of them
+fn covers_tcp_port(rule: &SecurityGroupPolicy, port: u16) -> bool {
+    let tcp = rule.protocol.is_none_or(|p| matches!(p, Protocol::Tcp | Protocol::All));
+    tcp && rule.port.as_deref().is_none_or(|ports| {
+        ports.split(',').map(str::trim).any(|p| match p.split_once('-') {
+            _ if p.eq_ignore_ascii_case("ALL") => true,
+            Some((from, to)) => match (from.trim().parse::<u16>(), to.trim().parse::<u16>()) {
+                (Ok(from), Ok(to)) => (from..=to).contains(&port),
+                _ => false,
+            },
+            None => p.parse() == Ok(port),
+        })
+    })
+}
+
+/// id of the group named `config.name` in `region`, created if missing,
+/// with the missing rules added and the admin ports closed to anyone outside `admin_cidrs`
+pub async fn ensure_security_group(
+    client: &TencentCloudClient,
+    region: &Region,
+    config: &SecurityGroupConfig,
+) -> anyhow::Result<String> {
+    let security_group = client.cvm().security_group();
+    let existing = security_group.find_security_group(region, &config.name).await?;
+    let security_group_id = match existing {
+        Some(sg) => sg.security_group_id,
+        None => {
+            let request = CreateSecurityGroupRequest::new(&config.name, "palworld servers by pal-server-cli")
+                .tag("app", "palworld");
+            let sg = security_group.create_security_group(region, &request).await?;
+            println!(
+                "Created security group {} ({}) in {}",
+                sg.security_group_name, sg.security_group_id, region
+            );
+            sg.security_group_id
+        }
+    };
+
+    let policies = security_group
+        .describe_security_group_policies(region, &security_group_id)
+        .await?;
+
+    let exposed = policies
+        .ingress
+        .iter()
+        .filter(|rule| config.is_exposed(rule))
+        .map(|rule| SecurityGroupPolicy {
+            policy_index: None,
+            policy_description: None,
+            ..rule.clone()
+        })
+        .collect::<Vec<_>>();
+    if !exposed.is_empty() {
+        println!(
+            "Removing {} admin port rule(s) outside admin_cidrs from {}",
+            exposed.len(),
+            security_group_id
+        );
+        security_group
+            .delete_security_group_policies(
+                region,
+                &security_group_id,
+                SecurityGroupPolicySet {
+                    ingress: exposed,
+                    ..Default::default()
+                },
+            )
+            .await?;
+    }
+
+    // on top, in case the group ends with a drop-all rule
+    let missing = config
+        .ingress()
+        .into_iter()
+        .filter(|rule| !policies.ingress.iter().any(|r| r.same_rule(rule)))
+        .map(|rule| rule.index(0))
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        println!("Adding {} ingress rule(s) to {}", missing.len(), security_group_id);
+        security_group
+            .create_security_group_policies(
+                region,
+                &security_group_id,
+                SecurityGroupPolicySet {
+                    ingress: missing,
+                    ..Default::default()
+                },
+            )
+            .await?;
+    }
+    // a group without egress rules blocks all outbound traffic, e.g. steamcmd downloads
+    if policies.egress.is_empty() {
+        security_group
+            .create_security_group_policies(
+                region,
+                &security_group_id,
+                SecurityGroupPolicySet {
+                    egress: vec![SecurityGroupPolicy::accept(Protocol::All, "ALL", "0.0.0.0/0")],
+                    ..Default::default()
+                },
+            )
+            .await?;
+    }
+    Ok(security_group_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> SecurityGroupConfig {
+        SecurityGroupConfig {
+            ssh_cidrs: vec!["203.0.113.0/24".to_owned()],
+            rest_port: Some(8212),
+            rcon_port: Some(25575),
+            admin_cidrs: vec!["198.51.100.7/32".to_owned()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_ingress() {
+        let rules = config().ingress();
+        let expected = [
+            SecurityGroupPolicy::accept(Protocol::Udp, 8211, "0.0.0.0/0"),
+            SecurityGroupPolicy::accept(Protocol::Udp, 27015, "0.0.0.0/0"),
+            SecurityGroupPolicy::accept(Protocol::Tcp, 22, "203.0.113.0/24"),
+            SecurityGroupPolicy::accept(Protocol::Tcp, 8212, "198.51.100.7/32"),
+            SecurityGroupPolicy::accept(Protocol::Tcp, 25575, "198.51.100.7/32"),
+        ];
+        assert_eq!(rules.len(), expected.len());
+        for (rule, expected) in rules.iter().zip(&expected) {
+            assert!(rule.same_rule(expected), "{rule:?} != {expected:?}");
+        }
+
+        // no admin rules without admin_cidrs
+        let rules = SecurityGroupConfig {
+            admin_cidrs: vec![],
+            ..config()
+        }
+        .ingress();
+        assert_eq!(rules.len(), 3);
+    }
+
+    #[test]
+    fn test_is_exposed() {
+        let config = config();
+        assert!(config.is_exposed(&SecurityGroupPolicy::accept(Protocol::Tcp, 8212, "0.0.0.0/0")));
+        assert!(config.is_exposed(&SecurityGroupPolicy::accept(Protocol::Tcp, 25575, "10.0.0.0/8")));
+        assert!(!config.is_exposed(&SecurityGroupPolicy::accept(Protocol::Tcp, 8212, "198.51.100.7/32")));
+        // game and ssh ports are meant to be open
+        assert!(!config.is_exposed(&SecurityGroupPolicy::accept(Protocol::Udp, 8211, "0.0.0.0/0")));
+        assert!(!config.is_exposed(&SecurityGroupPolicy::accept(Protocol::Tcp, 22, "0.0.0.0/0")));
+        // a drop rule closes the port
+        let drop = SecurityGroupPolicy {
+            action: Some(PolicyAction::DROP),
+            ..SecurityGroupPolicy::accept(Protocol::Tcp, 8212, "0.0.0.0/0")
+        };
+        assert!(!config.is_exposed(&drop));
+
+        // rules covering the admin ports among others
+        for port in ["ALL", "8000-9000", "8212,25575", "80, 25575"] {
+            assert!(config.is_exposed(&SecurityGroupPolicy::accept(Protocol::Tcp, port, "0.0.0.0/0")));
+            assert!(config.is_exposed(&SecurityGroupPolicy::accept(Protocol::All, port, "0.0.0.0/0")));
+            assert!(!config.is_exposed(&SecurityGroupPolicy::accept(Protocol::Udp, port, "0.0.0.0/0")));
+        }
+        for port in ["8000-8100", "80,443", "8213"] {
+            assert!(!config.is_exposed(&SecurityGroupPolicy::accept(Protocol::Tcp, port, "0.0.0.0/0")));
+        }
+        assert!(config.is_exposed(&SecurityGroupPolicy::accept(Protocol::All, 8212, "0.0.0.0/0")));
+        assert!(!config.is_exposed(&SecurityGroupPolicy::accept(Protocol::Icmp, "ALL", "0.0.0.0/0")));
+    }
+}
@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use tencent_cloud_sdk::constant::InstanceType;
+use tencent_cloud_sdk::constant::{InstanceType, Region};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum ServiceInstanceType {
@@ -14,25 +14,14 @@ pub enum ServiceInstanceType {
     // T8C32G,
 }
 impl ServiceInstanceType {
-    pub fn to_list(&self) -> Vec<InstanceType> {
+    /// (cpu cores, memory GB), the instance types are looked up for each launch
+    pub fn spec(&self) -> (u32, u32) {
         match self {
-            ServiceInstanceType::T2C2G => vec![InstanceType::SA2Medium2, InstanceType::S5Medium2],
-            // ServiceInstanceType::T4C8G => vec![InstanceType::SA2Large8, InstanceType::SA3Large8],
-            ServiceInstanceType::T2C16G => vec![InstanceType::MA3Medium16, InstanceType::M5Medium16],
-            ServiceInstanceType::T4C16G => vec![
-                InstanceType::SA2Large16,
-                InstanceType::SA3Large16,
-                InstanceType::S5Large16,
-                InstanceType::S6Large16,
-                InstanceType::SA5Large16,
-            ],
-            ServiceInstanceType::T4C32G => vec![
-                InstanceType::MA3Large32,
-                InstanceType::MA2Large32,
-                InstanceType::M5Large32,
-                InstanceType::MA5Large32,
-            ],
-            // ServiceInstanceType::T8C32G => vec![InstanceType::SA22Xlarge32],
+            ServiceInstanceType::T2C2G => (2, 2),
+            ServiceInstanceType::T2C16G => (2, 16),
+            ServiceInstanceType::T4C16G => (4, 16),
+            ServiceInstanceType::T4C32G => (4, 32),
+            // ServiceInstanceType::T8C32G => (8, 32),
         }
     }
 }
@@ -42,11 +31,80 @@ pub struct Server {
     pub name: String,
     pub status: Status,
     pub service_instance_type: ServiceInstanceType,
-    // pub instance_type: InstanceType,
+    /// of the current instance
+    #[serde(default)]
+    pub instance_type: Option<InstanceType>,
     pub save: Option<String>,
     pub ip: Option<String>,
-    pub region: Option<String>,
+    pub region: Option<Region>,
     pub instance_id: Option<String>,
+    /// spot bid per hour, zones quoted above it are skipped
+    #[serde(default)]
+    pub max_price: Option<f64>,
+    /// of the current instance
+    #[serde(default)]
+    pub image_id: Option<String>,
+    /// holds `Pal/Saved` in disk storage mode, every instance is launched in its zone
+    #[serde(default)]
+    pub disk: Option<SaveDisk>,
+    /// taken by `--stop`, oldest first
+    #[serde(default)]
+    pub snapshot: Vec<DiskSnapshot>,
+    /// bound to every instance, which are launched in its region then
+    #[serde(default)]
+    pub eip: Option<ServerEip>,
+    /// A record pointed to the server ip, see `--hostname`
+    #[serde(default)]
+    pub hostname: Option<String>,
+}
+
+/// reserved elastic ip of a server, see `--eip`
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ServerEip {
+    pub region: Region,
+    pub address_id: String,
+    pub address_ip: String,
+}
+
+/// cbs data disk owned by a server, see `[launch.data_disk]`
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SaveDisk {
+    pub region: Region,
+    pub zone: String,
+    pub disk_id: String,
+}
+
+/// cbs snapshot of the data disk or of the system disk of a former instance
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct DiskSnapshot {
+    pub region: Region,
+    pub disk_id: String,
+    pub snapshot_id: String,
+}
+
+/// custom image with the palworld server installed, see `--bake-image`
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct BakedImage {
+    pub region: Region,
+    pub image_id: String,
+}
+
+impl Server {
+    /// region and zone every instance has to be launched in, for the data disk and the eip
+    pub fn placement(&self) -> (Option<&Region>, Option<&str>) {
+        match (&self.disk, &self.eip) {
+            (Some(disk), _) => (Some(&disk.region), Some(&disk.zone)),
+            (None, Some(eip)) => (Some(&eip.region), None),
+            (None, None) => (None, None),
+        }
+    }
+    /// region and id of the current instance
+    pub fn instance(&self) -> anyhow::Result<(Region, String)> {
+        match (&self.region, &self.instance_id) {
+            (Some(region), Some(instance_id)) => Ok((region.clone(), instance_id.clone())),
+            _ => anyhow::bail!("Server {} has no instance", self.name),
+        }
+    }
 }
 
 pub struct ServerManager {
@@ -57,13 +115,26 @@ pub struct ServerManager {
 #[derive(Deserialize, Serialize)]
 struct ServerManagerData {
     server: Vec<Server>,
+    /// one per candidate region
+    #[serde(default)]
+    image: Vec<BakedImage>,
+    /// replaced by a later bake, deleted once no instance was launched from them
+    #[serde(default)]
+    retired_image: Vec<BakedImage>,
 }
 
 impl ServerManager {
     pub fn new(path: &str) -> anyhow::Result<Self> {
         if !std::path::Path::new(path).exists() {
             // create empty file
-            std::fs::write(path, toml::to_string(&ServerManagerData { server: vec![] })?)?;
+            std::fs::write(
+                path,
+                toml::to_string(&ServerManagerData {
+                    server: vec![],
+                    image: vec![],
+                    retired_image: vec![],
+                })?,
+            )?;
         }
         let content = std::fs::read_to_string(path)?;
         let data: ServerManagerData = toml::from_str(&content)?;
@@ -82,6 +153,31 @@ impl ServerManager {
             .ok_or_else(|| anyhow::anyhow!("Server {} not found", name))
     }
 
+    pub fn list(&self) -> Vec<Server> {
+        self.data.server.clone()
+    }
+
+    pub fn images(&self) -> Vec<BakedImage> {
+        self.data.image.clone()
+    }
+
+    /// replace all baked images
+    pub fn set_images(&mut self, images: Vec<BakedImage>) -> anyhow::Result<()> {
+        self.data.image = images;
+        std::fs::write(&self.path, toml::to_string(&self.data)?)?;
+        Ok(())
+    }
+
+    pub fn retired_images(&self) -> Vec<BakedImage> {
+        self.data.retired_image.clone()
+    }
+
+    pub fn set_retired_images(&mut self, images: Vec<BakedImage>) -> anyhow::Result<()> {
+        self.data.retired_image = images;
+        std::fs::write(&self.path, toml::to_string(&self.data)?)?;
+        Ok(())
+    }
+
     pub fn add(&mut self, server: &Server) -> anyhow::Result<()> {
         if self.data.server.iter().any(|s| s.name == server.name) {
             anyhow::bail!("Server {} already exists", server.name);
@@ -108,4 +204,6 @@ pub enum Status {
     Running,
     Stopping,
     Stopped,
+    /// instance stopped without charging, disk kept
+    Paused,
 }
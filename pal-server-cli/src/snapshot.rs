@@ -0,0 +1,123 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+use tencent_cloud_sdk::{
+    client::{
+        TencentCloudClient,
+        cbs::{
+            cbs_disk::DiskState,
+            cbs_snapshot::{CreateSnapshotRequest, SnapshotState},
+        },
+    },
+    constant::Region,
+};
+use tokio::time::{Instant, sleep};
+
+use crate::server_status::DiskSnapshot;
+
+/// snapshot before every `--stop`, `[launch.snapshot]` in config file.
+/// the data disk in disk storage mode, the system disk otherwise
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SnapshotConfig {
+    /// per server, the oldest ones are deleted
+    pub keep: usize,
+}
+
+impl Default for SnapshotConfig {
+    fn default() -> Self {
+        Self { keep: 3 }
+    }
+}
+
+/// snapshot `disk_id` for the server `name` and wait until it is done
+pub async fn create_disk_snapshot(
+    client: &TencentCloudClient,
+    name: &str,
+    region: &Region,
+    disk_id: &str,
+) -> anyhow::Result<DiskSnapshot> {
+    let request = CreateSnapshotRequest::new(disk_id)
+        .snapshot_name(format!(
+            "psm-{}-{}",
+            name,
+            std::time::UNIX_EPOCH.elapsed().unwrap_or_default().as_secs()
+        ))
+        .tag("app", "palworld")
+        .tag("psm-server", name);
+    let snapshot_id = client.cbs().snapshots().create_snapshot(region, &request).await?;
+    println!("Creating snapshot {} of disk {} in {}", snapshot_id, disk_id, region);
+    wait_snapshot_ready(client, region, &snapshot_id, Duration::from_secs(1800)).await?;
+    Ok(DiskSnapshot {
+        region: region.clone(),
+        disk_id: disk_id.to_owned(),
+        snapshot_id,
+    })
+}
+
+/// poll until the snapshot is created
+pub async fn wait_snapshot_ready(
+    client: &TencentCloudClient,
+    region: &Region,
+    snapshot_id: &str,
+    timeout_duration: Duration,
+) -> anyhow::Result<()> {
+    let start_time = Instant::now();
+
+    loop {
+        let snapshot = client.cbs().snapshots().describe_snapshot(region, snapshot_id).await?;
+        match snapshot {
+            Some(s) if s.snapshot_state == SnapshotState::NORMAL => break Ok(()),
+            Some(s) => tracing::debug!(
+                "waiting snapshot {snapshot_id} to be ready, now {} {}%",
+                s.snapshot_state,
+                s.percent
+            ),
+            // not listed right after creating
+            None => tracing::debug!("waiting snapshot {snapshot_id} to be listed"),
+        }
+
+        if Instant::now() - start_time >= timeout_duration {
+            break Err(anyhow::anyhow!("wait snapshot {snapshot_id} timeout"));
+        }
+        sleep(Duration::from_secs(10)).await;
+    }
+}
+
+/// poll until the ApplySnapshot on the disk has run and the disk is UNATTACHED again
+pub async fn wait_disk_rollback(
+    client: &TencentCloudClient,
+    region: &Region,
+    disk_id: &str,
+    timeout_duration: Duration,
+) -> anyhow::Result<()> {
+    let start_time = Instant::now();
+    let mut started = false;
+
+    loop {
+        let disk = client
+            .cbs()
+            .disks()
+            .describe_disk(region, disk_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("data disk {disk_id} not found in {region}"))?;
+        let rolling = disk.rollbacking || disk.disk_state == DiskState::ROLLBACKING;
+        started |= rolling;
+        // a small disk may be rolled back before the first poll, the percent is that of the last rollback though
+        let finished =
+            started || (disk.rollback_percent == Some(100) && start_time.elapsed() >= Duration::from_secs(30));
+        if !rolling && finished && disk.disk_state == DiskState::UNATTACHED {
+            break Ok(());
+        }
+        tracing::debug!(
+            "waiting disk {disk_id} to be rolled back, now {} {:?}%",
+            disk.disk_state,
+            disk.rollback_percent
+        );
+
+        if Instant::now() - start_time >= timeout_duration {
+            break Err(anyhow::anyhow!("wait disk {disk_id} rollback timeout"));
+        }
+        sleep(Duration::from_secs(5)).await;
+    }
+}
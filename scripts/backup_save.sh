@@ -6,7 +6,8 @@ target_dir="/home/ubuntu/psm/saves"
 
 mkdir -p $target_dir
 
-cd /tmp/ && rm -rf ./Saved && cp -r $source_dir ./
+# the content, $source_dir may link to the data disk
+cd /tmp/ && rm -rf ./Saved && mkdir ./Saved && cp -r $source_dir/. ./Saved/
 
 name="Saved.$current_time.tar.gz"
 
@@ -0,0 +1,48 @@
+#!/bin/bash
+
+# $1: cbs disk id, Pal/Saved is linked to the disk mounted at $mount_dir
+
+disk_id=$1
+mount_dir="/data/psm"
+saved_dir="/home/ubuntu/Steam/steamapps/common/PalServer/Pal/Saved"
+
+dev="/dev/disk/by-id/virtio-$disk_id"
+retries=0
+while [ ! -e $dev ] && [ $retries -lt 30 ]
+do
+  sleep 2
+  retries=$((retries+1))
+done
+if [ ! -e $dev ]; then
+  echo "disk $disk_id not found"
+  exit 1
+fi
+
+# a new disk has no file system yet, blkid -p exits with 2 only when no signature is found.
+# any other failure must not format the save disk
+sudo blkid -p $dev
+probe=$?
+if [ $probe -eq 2 ]; then
+  if ! sudo mkfs.ext4 -F $dev; then
+    echo "failed to format disk $disk_id"
+    exit 1
+  fi
+elif [ $probe -ne 0 ]; then
+  echo "failed to probe disk $disk_id: $probe"
+  exit 1
+fi
+sudo mkdir -p $mount_dir
+if ! mountpoint -q $mount_dir && ! sudo mount $dev $mount_dir; then
+  echo "failed to mount disk $disk_id"
+  exit 1
+fi
+sudo chown ubuntu:ubuntu $mount_dir
+mkdir -p $mount_dir/Saved
+
+if [ ! -L $saved_dir ]; then
+  mkdir -p $(dirname $saved_dir)
+  rm -rf $saved_dir
+  ln -s $mount_dir/Saved $saved_dir
+fi
+
+echo "Disk mounted"
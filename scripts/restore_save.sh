@@ -6,6 +6,8 @@ dir="/home/ubuntu/psm/saves"
 find $dir -type f | grep "tar.gz" | sort -r | head -n 1 | xargs -I {} cp {} /tmp/
 cd /tmp/ && rm -rf ./Saved
 tar -zxvf Saved.*.tar.gz
-rm -rf /home/ubuntu/Steam/steamapps/common/PalServer/Pal/Saved
-cp -r ./Saved /home/ubuntu/Steam/steamapps/common/PalServer/Pal/Saved
+# keep Pal/Saved itself, it may link to the data disk
+saved_dir="/home/ubuntu/Steam/steamapps/common/PalServer/Pal/Saved"
+mkdir -p $saved_dir && rm -rf $saved_dir/*
+cp -r ./Saved/. $saved_dir/
 rm -rf Saved.*.tar.gz
@@ -0,0 +1,21 @@
+#!/bin/bash
+
+mount_dir="/data/psm"
+
+# let the server write the world before it is gone
+pkill -INT -f PalServer-Linux
+retries=0
+while pgrep -f PalServer-Linux > /dev/null && [ $retries -lt 30 ]
+do
+  sleep 1
+  retries=$((retries+1))
+done
+pkill -9 -f PalServer
+
+sync
+if mountpoint -q $mount_dir && ! sudo umount $mount_dir; then
+  echo "failed to unmount $mount_dir"
+  exit 1
+fi
+
+echo "Disk unmounted"
@@ -0,0 +1,155 @@
+use std::sync::Arc;
+
+use futures::{StreamExt, TryStreamExt, stream, stream::BoxStream};
+use serde::{Deserialize, Deserializer, Serialize, de::DeserializeOwned};
+
+use crate::{client::TencentCloudBaseClient, constant::Region, error::TencentCloudError};
+
+/// One Tencent Cloud api, e.g. `cvm:2017-03-12:DescribeInstances`.
+///
+/// `Response` is the content of the `Response` object, the envelope itself and
+/// `Response.Error` are handled by [`super::TencentCloudBaseClient::call`].
+pub trait Action {
+    const SERVICE: &'static str;
+    const VERSION: &'static str;
+    const ACTION: &'static str;
+    /// sending it twice has the same effect as once, e.g. Describe*, Delete* or with a `ClientToken`.
+    /// others are only retried when throttled, the first attempt may have been applied already.
+    const IDEMPOTENT: bool = true;
+    /// the response carries secrets, e.g. temporary credentials or a private key, and is never logged
+    const SENSITIVE: bool = false;
+
+    type Request: Serialize + Send + Sync;
+    type Response: DeserializeOwned;
+}
+
+/// `impl_action!(DescribeZones: "cvm", "2017-03-12", DescribeZonesRequest => DescribeZonesResponse);`
+/// the action name is the stringified type name.
+/// other consts may follow, e.g. `impl_action!(CreateImage: ..., IDEMPOTENT = false);`
+macro_rules! impl_action {
+    ($name:ident: $service:expr, $version:expr, $req:ty => $resp:ty $(, $flag:ident = $value:expr)* $(,)?) => {
+        pub struct $name;
+
+        impl $crate::client::action::Action for $name {
+            const SERVICE: &'static str = $service;
+            const VERSION: &'static str = $version;
+            const ACTION: &'static str = stringify!($name);
+            $(const $flag: bool = $value;)*
+
+            type Request = $req;
+            type Response = $resp;
+        }
+    };
+}
+pub(crate) use impl_action;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub(crate) struct ResponseEnvelope<T> {
+    pub response: T,
+}
+
+/// request of a Describe* api paged with `Offset` / `Limit`, see [`paged_stream`]
+pub trait PagedRequest: Clone + Send + Sync + 'static {
+    /// max `Limit` of the api, the page size if the request has none
+    const MAX_LIMIT: u64;
+
+    /// `(Offset, Limit)`
+    fn page(&self) -> (Option<u64>, Option<u64>);
+    fn with_page(self, offset: u64, limit: u64) -> Self;
+}
+
+/// response of a Describe* api paged with `Offset` / `Limit`
+pub trait PagedResponse {
+    type Item;
+
+    fn total_count(&self) -> u64;
+    fn into_items(self) -> Vec<Self::Item>;
+}
+
+/// walk every page of `A` starting from `request`'s offset, its limit is used as page size.
+/// stops once `TotalCount` items are fetched or a page comes back empty.
+pub(crate) fn paged_stream<A>(
+    client: Arc<TencentCloudBaseClient>,
+    region: &Region,
+    request: A::Request,
+) -> BoxStream<'static, Result<<A::Response as PagedResponse>::Item, TencentCloudError>>
+where
+    A: Action + 'static,
+    A::Request: PagedRequest,
+    A::Response: PagedResponse + Send,
+    <A::Response as PagedResponse>::Item: Send + 'static,
+{
+    let region = region.clone();
+    let (offset, limit) = request.page();
+    let limit = limit.unwrap_or(A::Request::MAX_LIMIT);
+
+    stream::try_unfold(Some(offset.unwrap_or_default()), move |offset| {
+        let client = client.clone();
+        let region = region.clone();
+        let request = request.clone();
+        async move {
+            let Some(offset) = offset else {
+                return Ok::<_, TencentCloudError>(None);
+            };
+            let page = client.call::<A>(&region, &request.with_page(offset, limit)).await?;
+            let total_count = page.total_count();
+            let items = page.into_items();
+            let fetched = offset + items.len() as u64;
+            let next = (!items.is_empty() && fetched < total_count).then_some(fetched);
+            Ok(Some((items, next)))
+        }
+    })
+    .map_ok(|items| stream::iter(items.into_iter().map(Ok)))
+    .try_flatten()
+    .boxed()
+}
+
+/// `Filters` item shared by the Describe* apis, e.g. `{"Name": "zone", "Values": ["ap-nanjing-1"]}`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Filter {
+    pub name: String,
+    pub values: Vec<String>,
+}
+
+impl Filter {
+    pub fn new(name: impl Into<String>, values: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            name: name.into(),
+            values: values.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Tag {
+    pub key: String,
+    pub value: String,
+}
+
+/// `#[serde(default, deserialize_with = "null_as_default")]` for lists the apis return as `null` when empty
+pub(crate) fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// `ClientToken` for the create apis that take one, so a retried request is applied only once
+pub(crate) fn client_token() -> String {
+    format!("{:016x}{:016x}", fastrand::u64(..), fastrand::u64(..))
+}
+
+/// request without any parameter
+#[derive(Debug, Default, Serialize)]
+pub struct EmptyRequest {}
+
+/// response that carries nothing but the request id
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct EmptyResponse {
+    pub request_id: String,
+}
@@ -0,0 +1,337 @@
+use serde::{Deserialize, Serialize};
+use strum::Display;
+use tracing::debug;
+
+use crate::{
+    client::{
+        action::{EmptyResponse, Filter, Tag, client_token, impl_action},
+        cvm::cvm_instance::Placement,
+    },
+    constant::Region,
+    error::Result,
+};
+
+use super::*;
+
+pub struct CBSDiskBuilder {
+    client: Arc<TencentCloudBaseClient>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display)]
+#[allow(non_camel_case_types)]
+pub enum DiskChargeType {
+    PREPAID,          // 包年包月
+    POSTPAID_BY_HOUR, // 按小时后付费
+    CDCPAID,          // 独享集群付费
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display)]
+#[allow(non_camel_case_types)]
+pub enum DiskState {
+    UNATTACHED,  // 未挂载
+    ATTACHING,   // 挂载中
+    ATTACHED,    // 已挂载
+    DETACHING,   // 解挂中
+    EXPANDING,   // 扩容中
+    ROLLBACKING, // 回滚中
+    TORECYCLE,   // 待回收
+    DUMPING,     // 拷贝硬盘中
+    #[serde(other)]
+    UNKNOWN,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display)]
+#[allow(non_camel_case_types)]
+pub enum DiskUsage {
+    SYSTEM_DISK, // 系统盘
+    DATA_DISK,   // 数据盘
+}
+
+/// CreateDisksRequest
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CreateDisksRequest {
+    pub placement: Placement,
+    pub disk_charge_type: DiskChargeType,
+    /// e.g. `CLOUD_PREMIUM`, `CLOUD_SSD`, `CLOUD_BSSD`
+    pub disk_type: String,
+    /// GB
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disk_size: Option<u32>,
+    /// the disk size defaults to the snapshot size
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshot_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disk_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disk_count: Option<u32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<Tag>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_token: Option<String>,
+}
+
+impl CreateDisksRequest {
+    /// one pay-as-you-go disk of `disk_size` GB
+    pub fn new(zone: impl Into<String>, disk_type: impl Into<String>, disk_size: u32) -> Self {
+        Self {
+            placement: Placement {
+                zone: zone.into(),
+                project_id: None,
+            },
+            disk_charge_type: DiskChargeType::POSTPAID_BY_HOUR,
+            disk_type: disk_type.into(),
+            disk_size: Some(disk_size),
+            snapshot_id: None,
+            disk_name: None,
+            disk_count: None,
+            tags: vec![],
+            client_token: None,
+        }
+    }
+    pub fn disk_name(mut self, disk_name: impl Into<String>) -> Self {
+        self.disk_name = Some(disk_name.into());
+        self
+    }
+    pub fn snapshot_id(mut self, snapshot_id: impl Into<String>) -> Self {
+        self.snapshot_id = Some(snapshot_id.into());
+        self
+    }
+    pub fn tag(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.tags.push(Tag {
+            key: key.into(),
+            value: value.into(),
+        });
+        self
+    }
+    pub fn client_token(mut self, client_token: impl Into<String>) -> Self {
+        self.client_token = Some(client_token.into());
+        self
+    }
+}
+
+/// CreateDisksResponse
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CreateDisksResponse {
+    pub disk_id_set: Vec<String>,
+}
+
+/// AttachDisksRequest
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AttachDisksRequest {
+    pub disk_ids: Vec<String>,
+    pub instance_id: String,
+    /// terminated with the instance
+    pub delete_with_instance: bool,
+}
+
+/// DetachDisksRequest
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DetachDisksRequest {
+    pub disk_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance_id: Option<String>,
+}
+
+/// DescribeDisksRequest
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DescribeDisksRequest {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub disk_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub filters: Vec<Filter>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+}
+
+/// DescribeDisksResponse
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DescribeDisksResponse {
+    pub total_count: usize,
+    pub disk_set: Vec<Disk>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Disk {
+    pub disk_id: String,
+    pub disk_name: String,
+    pub disk_type: String,
+    pub disk_usage: DiskUsage,
+    pub disk_charge_type: DiskChargeType,
+    /// GB
+    pub disk_size: u32,
+    pub disk_state: DiskState,
+    pub attached: bool,
+    /// empty if not attached
+    #[serde(default)]
+    pub instance_id: String,
+    pub placement: Placement,
+    #[serde(default)]
+    pub create_time: String,
+    /// an ApplySnapshot is in progress
+    #[serde(default)]
+    pub rollbacking: bool,
+    /// of the last ApplySnapshot
+    pub rollback_percent: Option<u32>,
+}
+
+/// TerminateDisksRequest
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct TerminateDisksRequest {
+    pub disk_ids: Vec<String>,
+}
+
+impl_action!(CreateDisks: SERVICE, VERSION, CreateDisksRequest => CreateDisksResponse);
+impl_action!(AttachDisks: SERVICE, VERSION, AttachDisksRequest => EmptyResponse);
+impl_action!(DetachDisks: SERVICE, VERSION, DetachDisksRequest => EmptyResponse);
+impl_action!(DescribeDisks: SERVICE, VERSION, DescribeDisksRequest => DescribeDisksResponse);
+impl_action!(TerminateDisks: SERVICE, VERSION, TerminateDisksRequest => EmptyResponse);
+
+impl CBSDiskBuilder {
+    pub fn new(client: Arc<TencentCloudBaseClient>) -> Self {
+        Self { client }
+    }
+    /// a `ClientToken` is generated if not set, the call is retried and must not create twice
+    pub async fn create_disks(&self, region: &Region, request: &CreateDisksRequest) -> Result<Vec<String>> {
+        let mut request = request.clone();
+        request.client_token.get_or_insert_with(client_token);
+        let body = self.client.call::<CreateDisks>(region, &request).await?;
+        debug!("body: {body:?}");
+        Ok(body.disk_id_set)
+    }
+    /// return the id of the only disk of `request`
+    pub async fn create_disk(&self, region: &Region, request: &CreateDisksRequest) -> Result<String> {
+        let ids = self.create_disks(region, request).await?;
+        Ok(ids.into_iter().next().unwrap_or_default())
+    }
+    /// attach to an instance in the same zone, the disk is kept when the instance is terminated
+    pub async fn attach_disk(&self, region: &Region, disk_id: &str, instance_id: &str) -> Result<()> {
+        let request = AttachDisksRequest {
+            disk_ids: vec![disk_id.to_owned()],
+            instance_id: instance_id.to_owned(),
+            delete_with_instance: false,
+        };
+        let body = self.client.call::<AttachDisks>(region, &request).await?;
+        debug!("body: {body:?}");
+        Ok(())
+    }
+    pub async fn detach_disk(&self, region: &Region, disk_id: &str) -> Result<()> {
+        let request = DetachDisksRequest {
+            disk_ids: vec![disk_id.to_owned()],
+            instance_id: None,
+        };
+        let body = self.client.call::<DetachDisks>(region, &request).await?;
+        debug!("body: {body:?}");
+        Ok(())
+    }
+    pub async fn describe_disks(&self, region: &Region, request: &DescribeDisksRequest) -> Result<Vec<Disk>> {
+        let body = self.client.call::<DescribeDisks>(region, request).await?;
+        Ok(body.disk_set)
+    }
+    pub async fn describe_disk(&self, region: &Region, disk_id: &str) -> Result<Option<Disk>> {
+        let request = DescribeDisksRequest {
+            disk_ids: vec![disk_id.to_owned()],
+            ..Default::default()
+        };
+        Ok(self.describe_disks(region, &request).await?.into_iter().next())
+    }
+    /// pay-as-you-go disks are released, unattached ones only
+    pub async fn terminate_disks(&self, region: &Region, disk_ids: Vec<String>) -> Result<()> {
+        let body = self
+            .client
+            .call::<TerminateDisks>(region, &TerminateDisksRequest { disk_ids })
+            .await?;
+        debug!("body: {body:?}");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        client::{RetryPolicy, TencentCloudClient, mock::MockServer},
+        config::{ClientConfig, Endpoint},
+    };
+
+    #[test]
+    fn test_disks() {
+        let request = CreateDisksRequest::new("ap-nanjing-1", "CLOUD_PREMIUM", 20)
+            .disk_name("psm-test")
+            .tag("app", "palworld");
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({
+                "Placement": {"Zone": "ap-nanjing-1"},
+                "DiskChargeType": "POSTPAID_BY_HOUR",
+                "DiskType": "CLOUD_PREMIUM",
+                "DiskSize": 20,
+                "DiskName": "psm-test",
+                "Tags": [{"Key": "app", "Value": "palworld"}],
+            })
+        );
+
+        let disk: Disk = serde_json::from_value(json!({
+            "DiskId": "disk-1",
+            "DiskName": "psm-test",
+            "DiskType": "CLOUD_PREMIUM",
+            "DiskUsage": "DATA_DISK",
+            "DiskChargeType": "POSTPAID_BY_HOUR",
+            "DiskSize": 20,
+            "DiskState": "ATTACHED",
+            "Attached": true,
+            "InstanceId": "ins-1",
+            "Placement": {"Zone": "ap-nanjing-1", "ProjectId": 0, "CageId": "", "CdcId": ""},
+            "CreateTime": "2024-01-25 12:00:00",
+            "Portable": true,
+            "Rollbacking": false,
+            "RollbackPercent": 100,
+        }))
+        .unwrap();
+        assert_eq!(disk.disk_state, DiskState::ATTACHED);
+        assert_eq!(disk.rollback_percent, Some(100));
+        assert_eq!(disk.placement.zone, "ap-nanjing-1");
+    }
+
+    #[tokio::test]
+    async fn test_create_disk_client_token() {
+        let server = MockServer::start(vec![
+            json!({"Response": {"Error": {"Code": "InternalError", "Message": "oops"}, "RequestId": "req-1"}}),
+            json!({"Response": {"DiskIdSet": ["disk-1"], "RequestId": "req-2"}}),
+        ])
+        .await;
+        let config = ClientConfig::new("ak", "sk")
+            .endpoint(Endpoint::Custom(server.url.clone()))
+            .retry_policy(RetryPolicy {
+                base_delay: Duration::from_millis(1),
+                ..Default::default()
+            });
+        let client = TencentCloudClient::new(&config).unwrap();
+
+        let request = CreateDisksRequest::new("ap-nanjing-1", "CLOUD_PREMIUM", 20);
+        let disk_id = client
+            .cbs()
+            .disks()
+            .create_disk(&Region::Nanjing, &request)
+            .await
+            .unwrap();
+        assert_eq!(disk_id, "disk-1");
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].json()["ClientToken"].is_string());
+        assert_eq!(requests[1].json()["ClientToken"], requests[0].json()["ClientToken"]);
+    }
+}
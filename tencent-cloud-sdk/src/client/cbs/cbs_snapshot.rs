@@ -0,0 +1,230 @@
+use serde::{Deserialize, Serialize};
+use strum::Display;
+use tracing::debug;
+
+use crate::{
+    client::action::{EmptyResponse, Filter, Tag, impl_action},
+    constant::Region,
+    error::Result,
+};
+
+use super::{cbs_disk::DiskUsage, *};
+
+pub struct CBSSnapshotBuilder {
+    client: Arc<TencentCloudBaseClient>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display)]
+#[allow(non_camel_case_types)]
+pub enum SnapshotState {
+    NORMAL,              // 正常
+    CREATING,            // 创建中
+    ROLLBACKING,         // 回滚中
+    COPYING_FROM_REMOTE, // 跨地域复制中
+    CHECKING_COPIED,     // 复制校验中
+    TORECYCLE,           // 待回收
+    #[serde(other)]
+    UNKNOWN,
+}
+
+/// CreateSnapshotRequest
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CreateSnapshotRequest {
+    pub disk_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshot_name: Option<String>,
+    /// deleted automatically after, e.g. `2024-02-01T00:00:00+08:00`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deadline: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<Tag>,
+}
+
+impl CreateSnapshotRequest {
+    pub fn new(disk_id: impl Into<String>) -> Self {
+        Self {
+            disk_id: disk_id.into(),
+            snapshot_name: None,
+            deadline: None,
+            tags: vec![],
+        }
+    }
+    pub fn snapshot_name(mut self, snapshot_name: impl Into<String>) -> Self {
+        self.snapshot_name = Some(snapshot_name.into());
+        self
+    }
+    pub fn deadline(mut self, deadline: impl Into<String>) -> Self {
+        self.deadline = Some(deadline.into());
+        self
+    }
+    pub fn tag(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.tags.push(Tag {
+            key: key.into(),
+            value: value.into(),
+        });
+        self
+    }
+}
+
+/// CreateSnapshotResponse
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CreateSnapshotResponse {
+    pub snapshot_id: String,
+}
+
+/// DescribeSnapshotsRequest
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DescribeSnapshotsRequest {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub snapshot_ids: Vec<String>,
+    /// e.g. `disk-id`, `snapshot-name`, `snapshot-state`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub filters: Vec<Filter>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+}
+
+/// DescribeSnapshotsResponse
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DescribeSnapshotsResponse {
+    pub total_count: usize,
+    pub snapshot_set: Vec<Snapshot>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Snapshot {
+    pub snapshot_id: String,
+    pub snapshot_name: String,
+    pub snapshot_state: SnapshotState,
+    /// the source disk, may be terminated already
+    pub disk_id: String,
+    pub disk_usage: DiskUsage,
+    /// GB
+    pub disk_size: u32,
+    /// of creating
+    #[serde(default)]
+    pub percent: u32,
+    pub create_time: String,
+}
+
+/// DeleteSnapshotsRequest
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DeleteSnapshotsRequest {
+    pub snapshot_ids: Vec<String>,
+    /// delete the images created from the snapshots too
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delete_bind_images: Option<bool>,
+}
+
+/// ApplySnapshotRequest
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ApplySnapshotRequest {
+    pub snapshot_id: String,
+    /// the source disk of the snapshot
+    pub disk_id: String,
+    /// stop the instance the disk is attached to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_stop_instance: Option<bool>,
+    /// start it again afterwards
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_start_instance: Option<bool>,
+}
+
+impl_action!(CreateSnapshot: SERVICE, VERSION, CreateSnapshotRequest => CreateSnapshotResponse, IDEMPOTENT = false);
+impl_action!(DescribeSnapshots: SERVICE, VERSION, DescribeSnapshotsRequest => DescribeSnapshotsResponse);
+impl_action!(DeleteSnapshots: SERVICE, VERSION, DeleteSnapshotsRequest => EmptyResponse);
+impl_action!(ApplySnapshot: SERVICE, VERSION, ApplySnapshotRequest => EmptyResponse);
+
+impl CBSSnapshotBuilder {
+    pub fn new(client: Arc<TencentCloudBaseClient>) -> Self {
+        Self { client }
+    }
+    /// return the snapshot id, usable once NORMAL
+    pub async fn create_snapshot(&self, region: &Region, request: &CreateSnapshotRequest) -> Result<String> {
+        let body = self.client.call::<CreateSnapshot>(region, request).await?;
+        Ok(body.snapshot_id)
+    }
+    pub async fn describe_snapshots(
+        &self,
+        region: &Region,
+        request: &DescribeSnapshotsRequest,
+    ) -> Result<Vec<Snapshot>> {
+        let body = self.client.call::<DescribeSnapshots>(region, request).await?;
+        Ok(body.snapshot_set)
+    }
+    pub async fn describe_snapshot(&self, region: &Region, snapshot_id: &str) -> Result<Option<Snapshot>> {
+        let request = DescribeSnapshotsRequest {
+            snapshot_ids: vec![snapshot_id.to_owned()],
+            ..Default::default()
+        };
+        Ok(self.describe_snapshots(region, &request).await?.into_iter().next())
+    }
+    pub async fn delete_snapshots(&self, region: &Region, snapshot_ids: Vec<String>) -> Result<()> {
+        let request = DeleteSnapshotsRequest {
+            snapshot_ids,
+            delete_bind_images: None,
+        };
+        let body = self.client.call::<DeleteSnapshots>(region, &request).await?;
+        debug!("body: {body:?}");
+        Ok(())
+    }
+    /// roll the source disk back, it has to be unattached or its instance stopped
+    pub async fn apply_snapshot(&self, region: &Region, snapshot_id: &str, disk_id: &str) -> Result<()> {
+        let request = ApplySnapshotRequest {
+            snapshot_id: snapshot_id.to_owned(),
+            disk_id: disk_id.to_owned(),
+            auto_stop_instance: None,
+            auto_start_instance: None,
+        };
+        let body = self.client.call::<ApplySnapshot>(region, &request).await?;
+        debug!("body: {body:?}");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_snapshots() {
+        let request = CreateSnapshotRequest::new("disk-1")
+            .snapshot_name("psm-test-1")
+            .tag("psm-server", "test");
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({
+                "DiskId": "disk-1",
+                "SnapshotName": "psm-test-1",
+                "Tags": [{"Key": "psm-server", "Value": "test"}],
+            })
+        );
+
+        let snapshot: Snapshot = serde_json::from_value(json!({
+            "SnapshotId": "snap-1",
+            "SnapshotName": "psm-test-1",
+            "SnapshotState": "CREATING",
+            "DiskId": "disk-1",
+            "DiskUsage": "DATA_DISK",
+            "DiskSize": 20,
+            "Percent": 40,
+            "CreateTime": "2024-01-25 12:00:00",
+            "Placement": {"Zone": "ap-nanjing-1"},
+            "Encrypt": false,
+        }))
+        .unwrap();
+        assert_eq!(snapshot.snapshot_state, SnapshotState::CREATING);
+        assert_eq!(snapshot.percent, 40);
+    }
+}
@@ -0,0 +1,27 @@
+use std::sync::Arc;
+
+use super::TencentCloudBaseClient;
+
+pub mod cbs_disk;
+pub mod cbs_snapshot;
+
+const SERVICE: &str = "cbs";
+const VERSION: &str = "2017-03-12";
+
+pub struct CBSBuilder {
+    client: Arc<TencentCloudBaseClient>,
+}
+
+impl CBSBuilder {
+    pub fn new(client: Arc<TencentCloudBaseClient>) -> Self {
+        Self { client }
+    }
+
+    pub fn disks(&self) -> cbs_disk::CBSDiskBuilder {
+        cbs_disk::CBSDiskBuilder::new(self.client.clone())
+    }
+
+    pub fn snapshots(&self) -> cbs_snapshot::CBSSnapshotBuilder {
+        cbs_snapshot::CBSSnapshotBuilder::new(self.client.clone())
+    }
+}
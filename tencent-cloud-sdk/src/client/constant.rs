@@ -1,2 +1,4 @@
 pub const ACTION_HEADER: &str = "X-TC-Action";
-pub const REGION_HEADER: &str = "X-TC-Region";
\ No newline at end of file
+pub const REGION_HEADER: &str = "X-TC-Region";
+pub const LANGUAGE_HEADER: &str = "X-TC-Language";
+pub const TOKEN_HEADER: &str = "X-TC-Token";
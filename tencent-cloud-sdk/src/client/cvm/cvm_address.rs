@@ -0,0 +1,261 @@
+use serde::{Deserialize, Serialize};
+use strum::Display;
+use tracing::debug;
+
+use crate::{
+    client::action::{Filter, Tag, impl_action},
+    constant::Region,
+    error::Result,
+};
+
+use super::{cvm_instance::InternetChargeType, *};
+
+/// elastic public ips
+pub struct AddressBuilder {
+    client: Arc<TencentCloudBaseClient>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display)]
+#[allow(non_camel_case_types)]
+pub enum AddressStatus {
+    CREATING,  // 创建中
+    BINDING,   // 绑定中
+    BIND,      // 已绑定
+    UNBINDING, // 解绑中
+    UNBIND,    // 已解绑
+    OFFLINING, // 释放中
+    BIND_ENI,  // 绑定悬空弹性网卡
+    #[serde(other)]
+    UNKNOWN,
+}
+
+/// AllocateAddressesRequest
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AllocateAddressesRequest {
+    pub address_count: u32,
+    /// the bandwidth of the bound instance is used if not set, for standard accounts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub internet_charge_type: Option<InternetChargeType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub internet_max_bandwidth_out: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address_name: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<Tag>,
+}
+
+impl AllocateAddressesRequest {
+    /// one eip
+    pub fn new() -> Self {
+        Self {
+            address_count: 1,
+            internet_charge_type: None,
+            internet_max_bandwidth_out: None,
+            address_name: None,
+            tags: vec![],
+        }
+    }
+    pub fn bandwidth(mut self, internet_charge_type: InternetChargeType, internet_max_bandwidth_out: u32) -> Self {
+        self.internet_charge_type = Some(internet_charge_type);
+        self.internet_max_bandwidth_out = Some(internet_max_bandwidth_out);
+        self
+    }
+    pub fn address_name(mut self, address_name: impl Into<String>) -> Self {
+        self.address_name = Some(address_name.into());
+        self
+    }
+    pub fn tag(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.tags.push(Tag {
+            key: key.into(),
+            value: value.into(),
+        });
+        self
+    }
+}
+
+impl Default for AllocateAddressesRequest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// AllocateAddressesResponse
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AllocateAddressesResponse {
+    pub address_set: Vec<String>,
+    #[serde(default)]
+    pub task_id: Option<String>,
+}
+
+/// AssociateAddressRequest
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AssociateAddressRequest {
+    pub address_id: String,
+    pub instance_id: String,
+}
+
+/// DisassociateAddressRequest
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DisassociateAddressRequest {
+    pub address_id: String,
+    /// give the instance a normal public ip back
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reallocate_normal_public_ip: Option<bool>,
+}
+
+/// ReleaseAddressesRequest
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ReleaseAddressesRequest {
+    pub address_ids: Vec<String>,
+}
+
+/// AssociateAddress / DisassociateAddress / ReleaseAddresses are done asynchronously
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AddressTaskResponse {
+    #[serde(default)]
+    pub task_id: Option<String>,
+}
+
+/// DescribeAddressesRequest
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DescribeAddressesRequest {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub address_ids: Vec<String>,
+    /// e.g. `address-ip`, `address-name`, `instance-id`, `address-status`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub filters: Vec<Filter>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+}
+
+/// DescribeAddressesResponse
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DescribeAddressesResponse {
+    pub total_count: usize,
+    pub address_set: Vec<Address>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Address {
+    pub address_id: String,
+    #[serde(default)]
+    pub address_name: Option<String>,
+    pub address_status: AddressStatus,
+    pub address_ip: String,
+    /// bound instance
+    #[serde(default)]
+    pub instance_id: Option<String>,
+    pub created_time: String,
+}
+
+impl_action!(AllocateAddresses: VPC_SERVICE, VPC_VERSION, AllocateAddressesRequest => AllocateAddressesResponse, IDEMPOTENT = false);
+impl_action!(AssociateAddress: VPC_SERVICE, VPC_VERSION, AssociateAddressRequest => AddressTaskResponse);
+impl_action!(DisassociateAddress: VPC_SERVICE, VPC_VERSION, DisassociateAddressRequest => AddressTaskResponse);
+impl_action!(ReleaseAddresses: VPC_SERVICE, VPC_VERSION, ReleaseAddressesRequest => AddressTaskResponse);
+impl_action!(DescribeAddresses: VPC_SERVICE, VPC_VERSION, DescribeAddressesRequest => DescribeAddressesResponse);
+
+impl AddressBuilder {
+    pub fn new(client: Arc<TencentCloudBaseClient>) -> Self {
+        Self { client }
+    }
+    /// return the eip ids, charged for idle time while not bound
+    pub async fn allocate_addresses(&self, region: &Region, request: &AllocateAddressesRequest) -> Result<Vec<String>> {
+        let body = self.client.call::<AllocateAddresses>(region, request).await?;
+        debug!("body: {body:?}");
+        Ok(body.address_set)
+    }
+    /// replaces the normal public ip of the instance
+    pub async fn associate_address(&self, region: &Region, address_id: &str, instance_id: &str) -> Result<()> {
+        let request = AssociateAddressRequest {
+            address_id: address_id.to_owned(),
+            instance_id: instance_id.to_owned(),
+        };
+        let body = self.client.call::<AssociateAddress>(region, &request).await?;
+        debug!("body: {body:?}");
+        Ok(())
+    }
+    pub async fn disassociate_address(&self, region: &Region, address_id: &str) -> Result<()> {
+        let request = DisassociateAddressRequest {
+            address_id: address_id.to_owned(),
+            reallocate_normal_public_ip: None,
+        };
+        let body = self.client.call::<DisassociateAddress>(region, &request).await?;
+        debug!("body: {body:?}");
+        Ok(())
+    }
+    pub async fn release_addresses(&self, region: &Region, address_ids: Vec<String>) -> Result<()> {
+        let body = self
+            .client
+            .call::<ReleaseAddresses>(region, &ReleaseAddressesRequest { address_ids })
+            .await?;
+        debug!("body: {body:?}");
+        Ok(())
+    }
+    pub async fn describe_addresses(
+        &self,
+        region: &Region,
+        request: &DescribeAddressesRequest,
+    ) -> Result<Vec<Address>> {
+        let body = self.client.call::<DescribeAddresses>(region, request).await?;
+        Ok(body.address_set)
+    }
+    pub async fn describe_address(&self, region: &Region, address_id: &str) -> Result<Option<Address>> {
+        let request = DescribeAddressesRequest {
+            address_ids: vec![address_id.to_owned()],
+            ..Default::default()
+        };
+        Ok(self.describe_addresses(region, &request).await?.into_iter().next())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_addresses() {
+        let request = AllocateAddressesRequest::new()
+            .address_name("psm-test")
+            .tag("psm-server", "test");
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({
+                "AddressCount": 1,
+                "AddressName": "psm-test",
+                "Tags": [{"Key": "psm-server", "Value": "test"}],
+            })
+        );
+
+        let address: Address = serde_json::from_value(json!({
+            "AddressId": "eip-1",
+            "AddressName": "psm-test",
+            "AddressStatus": "BIND",
+            "AddressIp": "203.0.113.7",
+            "InstanceId": "ins-1",
+            "CreatedTime": "2024-01-25T12:00:00Z",
+            "NetworkInterfaceId": null,
+            "PrivateAddressIp": null,
+            "IsArrears": false,
+            "IsBlocked": false,
+            "IsEipDirectConnection": false,
+            "AddressType": "EIP",
+            "CascadeRelease": false,
+        }))
+        .unwrap();
+        assert_eq!(address.address_status, AddressStatus::BIND);
+        assert_eq!(address.instance_id.as_deref(), Some("ins-1"));
+    }
+}
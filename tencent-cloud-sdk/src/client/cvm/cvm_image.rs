@@ -0,0 +1,440 @@
+use chrono::{DateTime, Utc};
+use futures::{TryStreamExt, stream::BoxStream};
+use serde::{Deserialize, Serialize};
+use strum::Display;
+use tracing::debug;
+
+use crate::{
+    client::{
+        action::{EmptyResponse, Filter, PagedRequest, PagedResponse, Tag, impl_action, paged_stream},
+        cvm::cvm_instance::TagSpecification,
+    },
+    constant::Region,
+    error::Result,
+};
+
+use super::*;
+
+pub struct CVMImageBuilder {
+    client: Arc<TencentCloudBaseClient>,
+}
+
+/// DescribeImagesRequest
+///
+/// `ImageIds` and `Filters` can't be used at the same time.
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DescribeImagesRequest {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub image_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub filters: Vec<Filter>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u64>,
+    /// 20 by default, at most 100
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u64>,
+}
+
+impl DescribeImagesRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn image_ids(mut self, image_ids: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.image_ids = image_ids.into_iter().map(Into::into).collect();
+        self
+    }
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+    pub fn image_type(self, image_type: ImageType) -> Self {
+        self.filter(Filter::new("image-type", [image_type.to_string()]))
+    }
+    pub fn image_name(self, name: &str) -> Self {
+        self.filter(Filter::new("image-name", [name]))
+    }
+    /// os family, e.g. `Ubuntu`, `CentOS`, `Windows`
+    pub fn platform(self, platform: &str) -> Self {
+        self.filter(Filter::new("platform", [platform]))
+    }
+    pub fn tag(self, key: &str, value: &str) -> Self {
+        self.filter(Filter::new(format!("tag:{key}"), [value]))
+    }
+    pub fn offset(mut self, offset: u64) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+    pub fn limit(mut self, limit: u64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
+
+/// DescribeImagesResponse
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DescribeImagesResponse {
+    pub total_count: u64,
+    pub image_set: Vec<Image>,
+}
+
+impl PagedRequest for DescribeImagesRequest {
+    const MAX_LIMIT: u64 = 100;
+
+    fn page(&self) -> (Option<u64>, Option<u64>) {
+        (self.offset, self.limit)
+    }
+    fn with_page(self, offset: u64, limit: u64) -> Self {
+        self.offset(offset).limit(limit)
+    }
+}
+
+impl PagedResponse for DescribeImagesResponse {
+    type Item = Image;
+
+    fn total_count(&self) -> u64 {
+        self.total_count
+    }
+    fn into_items(self) -> Vec<Image> {
+        self.image_set
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Image {
+    pub image_id: String,
+    pub image_name: String,
+    /// e.g. `Ubuntu Server 22.04 LTS 64bit`
+    pub os_name: String,
+    pub image_type: ImageType,
+    pub image_state: ImageState,
+    /// e.g. `Ubuntu`
+    pub platform: String,
+    /// e.g. `x86_64`
+    pub architecture: String,
+    /// GB
+    pub image_size: u32,
+    #[serde(default)]
+    pub image_description: String,
+    pub created_time: Option<DateTime<Utc>>,
+    /// percentage of a SyncImages in progress
+    pub sync_percent: Option<u32>,
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+}
+
+impl Image {
+    pub fn tag(&self, key: &str) -> Option<&str> {
+        self.tags.iter().find(|t| t.key == key).map(|t| t.value.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Display)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum ImageType {
+    #[allow(non_camel_case_types)]
+    PRIVATE_IMAGE, //自定义镜像
+    #[allow(non_camel_case_types)]
+    PUBLIC_IMAGE, //公共镜像
+    #[allow(non_camel_case_types)]
+    SHARED_IMAGE, //共享镜像
+    #[serde(other)]
+    UNKNOWN,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Display)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum ImageState {
+    CREATING,     //创建中
+    NORMAL,       //正常
+    CREATEFAILED, //创建失败
+    USING,        //使用中
+    SYNCING,      //同步中
+    IMPORTING,    //导入中
+    IMPORTFAILED, //导入失败
+    #[serde(other)]
+    UNKNOWN,
+}
+
+impl ImageState {
+    /// instances can be launched from it
+    pub fn is_ready(&self) -> bool {
+        matches!(self, ImageState::NORMAL | ImageState::USING)
+    }
+}
+
+/// CreateImageRequest
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CreateImageRequest {
+    pub image_name: String,
+    pub instance_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_description: Option<String>,
+    /// `"TRUE"` to power off a running instance that fails to shut down normally
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub force_poweroff: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tag_specification: Vec<TagSpecification>,
+}
+
+impl CreateImageRequest {
+    pub fn new(image_name: impl Into<String>, instance_id: impl Into<String>) -> Self {
+        Self {
+            image_name: image_name.into(),
+            instance_id: instance_id.into(),
+            image_description: None,
+            force_poweroff: None,
+            tag_specification: vec![],
+        }
+    }
+    pub fn image_description(mut self, description: impl Into<String>) -> Self {
+        self.image_description = Some(description.into());
+        self
+    }
+    pub fn force_poweroff(mut self, force: bool) -> Self {
+        self.force_poweroff = Some(if force { "TRUE" } else { "FALSE" }.to_owned());
+        self
+    }
+    /// tag the image
+    pub fn tag(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        let tag = Tag {
+            key: key.into(),
+            value: value.into(),
+        };
+        match self.tag_specification.iter_mut().find(|t| t.resource_type == "image") {
+            Some(spec) => spec.tags.push(tag),
+            None => self.tag_specification.push(TagSpecification {
+                resource_type: "image".to_owned(),
+                tags: vec![tag],
+            }),
+        }
+        self
+    }
+}
+
+/// CreateImageResponse
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CreateImageResponse {
+    pub image_id: String,
+}
+
+/// DeleteImagesRequest
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DeleteImagesRequest {
+    pub image_ids: Vec<String>,
+    /// also delete the snapshots the images are made of
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delete_binded_snap: Option<bool>,
+}
+
+/// SyncImagesRequest
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SyncImagesRequest {
+    pub image_ids: Vec<String>,
+    pub destination_regions: Vec<Region>,
+    /// same name as the source if not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_name: Option<String>,
+    /// return the ids of the new images
+    pub image_set_required: bool,
+}
+
+/// SyncImagesResponse
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SyncImagesResponse {
+    #[serde(default)]
+    pub image_set: Vec<SyncImage>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SyncImage {
+    pub image_id: String,
+    pub region: Region,
+}
+
+impl_action!(DescribeImages: SERVICE, VERSION, DescribeImagesRequest => DescribeImagesResponse);
+impl_action!(CreateImage: SERVICE, VERSION, CreateImageRequest => CreateImageResponse, IDEMPOTENT = false);
+impl_action!(DeleteImages: SERVICE, VERSION, DeleteImagesRequest => EmptyResponse);
+impl_action!(SyncImages: SERVICE, VERSION, SyncImagesRequest => SyncImagesResponse, IDEMPOTENT = false);
+
+impl CVMImageBuilder {
+    pub fn new(client: Arc<TencentCloudBaseClient>) -> Self {
+        Self { client }
+    }
+
+    /// one page of DescribeImages
+    pub async fn describe_images(
+        &self,
+        region: &Region,
+        request: &DescribeImagesRequest,
+    ) -> Result<DescribeImagesResponse> {
+        self.client.call::<DescribeImages>(region, request).await
+    }
+
+    pub async fn describe_image(&self, region: &Region, image_id: &str) -> Result<Option<Image>> {
+        let request = DescribeImagesRequest::new().image_ids([image_id]);
+        let body = self.describe_images(region, &request).await?;
+        Ok(body.image_set.into_iter().find(|i| i.image_id == image_id))
+    }
+
+    /// walk every page starting from `request.offset`, `request.limit` is used as page size
+    pub fn describe_images_stream(
+        &self,
+        region: &Region,
+        request: DescribeImagesRequest,
+    ) -> BoxStream<'static, Result<Image>> {
+        paged_stream::<DescribeImages>(self.client.clone(), region, request)
+    }
+
+    /// newest public base image of `platform` and `architecture` whose os name contains `os_name`,
+    /// e.g. `("Ubuntu", "22.04", "x86_64")`. variants like GPU driver images have longer names and lose.
+    pub async fn find_public_image(
+        &self,
+        region: &Region,
+        platform: &str,
+        os_name: &str,
+        architecture: &str,
+    ) -> Result<Option<Image>> {
+        let request = DescribeImagesRequest::new()
+            .image_type(ImageType::PUBLIC_IMAGE)
+            .platform(platform);
+        let images: Vec<Image> = self
+            .describe_images_stream(region, request)
+            .try_filter(|i| {
+                std::future::ready(
+                    i.os_name.contains(os_name) && i.architecture == architecture && i.image_state.is_ready(),
+                )
+            })
+            .try_collect()
+            .await?;
+        Ok(images
+            .into_iter()
+            .min_by_key(|i| (i.os_name.len(), std::cmp::Reverse(i.created_time))))
+    }
+
+    /// the image is CREATING until the snapshot is done, the instance is shut down meanwhile if it is running
+    pub async fn create_image(&self, region: &Region, request: &CreateImageRequest) -> Result<String> {
+        let body = self.client.call::<CreateImage>(region, request).await?;
+        debug!("body: {body:?}");
+        Ok(body.image_id)
+    }
+
+    pub async fn delete_images(&self, region: &Region, image_ids: Vec<String>, delete_binded_snap: bool) -> Result<()> {
+        let request = DeleteImagesRequest {
+            image_ids,
+            delete_binded_snap: Some(delete_binded_snap),
+        };
+        let body = self.client.call::<DeleteImages>(region, &request).await?;
+        debug!("body: {body:?}");
+        Ok(())
+    }
+
+    /// copy a private image of `region` to `destination_regions`, the copies are SYNCING until done
+    pub async fn sync_images(
+        &self,
+        region: &Region,
+        image_id: &str,
+        destination_regions: Vec<Region>,
+    ) -> Result<Vec<SyncImage>> {
+        let request = SyncImagesRequest {
+            image_ids: vec![image_id.to_owned()],
+            destination_regions,
+            image_name: None,
+            image_set_required: true,
+        };
+        let body = self.client.call::<SyncImages>(region, &request).await?;
+        debug!("body: {body:?}");
+        Ok(body.image_set)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        client::{TencentCloudClient, mock::MockServer},
+        config::{ClientConfig, Endpoint},
+    };
+
+    #[test]
+    fn test_deserialize_image() {
+        let image: Image = serde_json::from_value(json!({
+            "ImageId": "img-487zeit5",
+            "OsName": "Ubuntu Server 22.04 LTS 64bit",
+            "ImageType": "PUBLIC_IMAGE",
+            "CreatedTime": "2022-05-12T06:03:39Z",
+            "ImageName": "Ubuntu Server 22.04 LTS 64bit",
+            "ImageDescription": "Ubuntu Server 22.04 LTS 64bit",
+            "ImageSize": 20,
+            "Architecture": "x86_64",
+            "ImageState": "NORMAL",
+            "Platform": "Ubuntu",
+            "ImageCreator": "",
+            "ImageSource": "OFFICIAL",
+            "SyncPercent": null,
+            "IsSupportCloudinit": true,
+            "SnapshotSet": [],
+            "Tags": [],
+            "LicenseType": "TencentCloud",
+        }))
+        .unwrap();
+        assert_eq!(image.image_type, ImageType::PUBLIC_IMAGE);
+        assert!(image.image_state.is_ready());
+
+        let request = SyncImagesRequest {
+            image_ids: vec!["img-1".to_owned()],
+            destination_regions: vec![Region::Nanjing, Region::Shanghai],
+            image_name: None,
+            image_set_required: true,
+        };
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({"ImageIds": ["img-1"], "DestinationRegions": ["ap-nanjing", "ap-shanghai"], "ImageSetRequired": true})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_find_public_image() {
+        let image = |id: &str, os_name: &str, architecture: &str, created: &str| {
+            json!({
+                "ImageId": id, "ImageName": os_name, "OsName": os_name, "ImageType": "PUBLIC_IMAGE",
+                "ImageState": "NORMAL", "Platform": "Ubuntu", "Architecture": architecture, "ImageSize": 20,
+                "CreatedTime": created,
+            })
+        };
+        let server = MockServer::start(vec![
+            json!({"Response": {"TotalCount": 4, "ImageSet": [
+                image("img-old", "Ubuntu Server 22.04 LTS 64bit", "x86_64", "2022-05-12T06:03:39Z"),
+                image("img-arm", "Ubuntu Server 22.04 LTS 64bit", "arm", "2024-05-12T06:03:39Z"),
+            ], "RequestId": "req-1"}}),
+            json!({"Response": {"TotalCount": 4, "ImageSet": [
+                image("img-gpu", "Ubuntu Server 22.04 LTS 64bit GPU", "x86_64", "2024-06-12T06:03:39Z"),
+                image("img-new", "Ubuntu Server 22.04 LTS 64bit", "x86_64", "2024-01-12T06:03:39Z"),
+            ], "RequestId": "req-2"}}),
+        ])
+        .await;
+        let config = ClientConfig::new("ak", "sk").endpoint(Endpoint::Custom(server.url.clone()));
+        let client = TencentCloudClient::new(&config).unwrap();
+
+        let image = client
+            .cvm()
+            .images()
+            .find_public_image(&Region::Nanjing, "Ubuntu", "22.04", "x86_64")
+            .await
+            .unwrap();
+        assert_eq!(image.map(|i| i.image_id).as_deref(), Some("img-new"));
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].json()["Offset"], 2);
+    }
+}
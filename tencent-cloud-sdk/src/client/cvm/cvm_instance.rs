@@ -1,44 +1,202 @@
-use reqwest::StatusCode;
-use serde::Deserialize;
-use serde_json::json;
+use base64::{Engine, prelude::BASE64_STANDARD};
+use chrono::{DateTime, Utc};
+use futures::stream::BoxStream;
+use serde::{Deserialize, Serialize};
+use strum::Display;
 use tracing::debug;
 
 use crate::{
-    client::constant::{ACTION_HEADER, REGION_HEADER},
+    client::action::{
+        EmptyResponse, Filter, PagedRequest, PagedResponse, Tag, client_token, impl_action, null_as_default,
+        paged_stream,
+    },
     constant::{InstanceType, Region},
+    error::{Result, TencentCloudError},
 };
 
 use super::*;
 
 pub struct CVMInstanceBuilder {
     client: Arc<TencentCloudBaseClient>,
-    service_name: String,
-    version: String,
 }
 
-/// DescribeInstancesResponse
-#[derive(Debug, Deserialize)]
+/// DescribeInstancesRequest
+///
+/// `InstanceIds` and `Filters` can't be used at the same time.
+#[derive(Debug, Default, Clone, Serialize)]
 #[serde(rename_all = "PascalCase")]
-pub struct DescribeInstancesResponse {
-    pub response: DescribeInstancesResponseInner,
+pub struct DescribeInstancesRequest {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub instance_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub filters: Vec<Filter>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u64>,
+}
+
+impl DescribeInstancesRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn instance_ids(mut self, instance_ids: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.instance_ids = instance_ids.into_iter().map(Into::into).collect();
+        self
+    }
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+    pub fn zone(self, zone: &str) -> Self {
+        self.filter(Filter::new("zone", [zone]))
+    }
+    pub fn tag(self, key: &str, value: &str) -> Self {
+        self.filter(Filter::new(format!("tag:{key}"), [value]))
+    }
+    pub fn instance_name(self, name: &str) -> Self {
+        self.filter(Filter::new("instance-name", [name]))
+    }
+    pub fn instance_state(self, state: InstanceState) -> Self {
+        self.filter(Filter::new("instance-state", [state.to_string()]))
+    }
+    pub fn offset(mut self, offset: u64) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+    pub fn limit(mut self, limit: u64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
 }
 
+/// DescribeInstancesResponse
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "PascalCase")]
-pub struct DescribeInstancesResponseInner {
+pub struct DescribeInstancesResponse {
+    pub total_count: u64,
     pub instance_set: Vec<Instance>,
 }
 
+impl PagedRequest for DescribeInstancesRequest {
+    const MAX_LIMIT: u64 = 100;
+
+    fn page(&self) -> (Option<u64>, Option<u64>) {
+        (self.offset, self.limit)
+    }
+    fn with_page(self, offset: u64, limit: u64) -> Self {
+        self.offset(offset).limit(limit)
+    }
+}
+
+impl PagedResponse for DescribeInstancesResponse {
+    type Item = Instance;
+
+    fn total_count(&self) -> u64 {
+        self.total_count
+    }
+    fn into_items(self) -> Vec<Instance> {
+        self.instance_set
+    }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct Instance {
+    pub instance_id: String,
+    pub instance_name: String,
     pub instance_state: InstanceState,
+    pub placement: Placement,
+    pub instance_type: InstanceType,
+    #[serde(rename = "CPU")]
+    pub cpu: u32,
+    /// GB
+    pub memory: u32,
+    pub instance_charge_type: InstanceChargeType,
+    pub image_id: String,
+    pub system_disk: SystemDisk,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub private_ip_addresses: Vec<String>,
     pub public_ip_addresses: Option<Vec<String>>,
-    pub instance_id: String,
-    // todo more
+    pub created_time: Option<DateTime<Utc>>,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub tags: Vec<Tag>,
+    /// last operation on this instance, e.g. `StopInstances`
+    pub latest_operation: Option<String>,
+    pub latest_operation_state: Option<LatestOperationState>,
+    pub latest_operation_request_id: Option<String>,
+    /// whether a STOPPED instance is still charged
+    pub stop_charging_mode: Option<StopChargingMode>,
+}
+
+impl Instance {
+    pub fn public_ip(&self) -> Option<&str> {
+        self.public_ip_addresses.as_ref()?.first().map(String::as_str)
+    }
+
+    pub fn tag(&self, key: &str) -> Option<&str> {
+        self.tags.iter().find(|t| t.key == key).map(|t| t.value.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Placement {
+    pub zone: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<i64>,
 }
 
-#[derive(Debug, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SystemDisk {
+    /// e.g. `CLOUD_PREMIUM`, `CLOUD_SSD`, `CLOUD_BSSD`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disk_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disk_id: Option<String>,
+    /// GB
+    pub disk_size: u32,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DataDisk {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disk_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disk_id: Option<String>,
+    /// GB
+    pub disk_size: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delete_with_instance: Option<bool>,
+    /// create the disk from this snapshot
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshot_id: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, Display)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum InstanceChargeType {
+    PREPAID, //预付费，即包年包月
+    #[allow(non_camel_case_types)]
+    POSTPAID_BY_HOUR, //按小时后付费
+    CDHPAID, //专用宿主机付费
+    SPOTPAID, //竞价付费
+    CDCPAID, //专用集群付费
+    #[serde(other)]
+    UNKNOWN,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Display)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum LatestOperationState {
+    SUCCESS,   //表示操作成功
+    OPERATING, //表示操作执行中
+    FAILED,    //表示操作失败
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq, Display)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum InstanceState {
     PENDING, //表示创建中
@@ -53,15 +211,317 @@ pub enum InstanceState {
     TERMINATING, //表示销毁中。
 }
 
-/// InquiryPriceRunInstancesResponse
-#[derive(Debug, Deserialize)]
+impl InstanceState {
+    /// the instance is on its way to another state, wait before the next operation
+    pub fn is_transitional(&self) -> bool {
+        matches!(
+            self,
+            InstanceState::PENDING
+                | InstanceState::STARTING
+                | InstanceState::STOPPING
+                | InstanceState::REBOOTING
+                | InstanceState::TERMINATING
+        )
+    }
+
+    /// the state StartInstances / StopInstances / RebootInstances / ResetInstance ends in
+    pub fn after(action: &str) -> Option<Self> {
+        match action {
+            "StartInstances" | "RebootInstances" | "ResetInstance" => Some(InstanceState::RUNNING),
+            "StopInstances" => Some(InstanceState::STOPPED),
+            _ => None,
+        }
+    }
+}
+
+/// how StopInstances / RebootInstances shut the instance down
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Display)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+pub enum StopType {
+    /// shut down the os normally, fails if it doesn't respond
+    Soft,
+    /// power off
+    Hard,
+    /// try soft first and power off if it fails, the default
+    SoftFirst,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+pub enum StopChargingMode {
+    KeepCharging,
+    /// only cpu/memory/public ip stop charging, the disks are still charged,
+    /// the public ip changes and resources may be sold out when started again
+    StopCharging,
+    /// in DescribeInstances only, e.g. prepaid instances
+    NotApplicable,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Display)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum InternetChargeType {
+    #[allow(non_camel_case_types)]
+    BANDWIDTH_PREPAID, //预付费按带宽结算
+    #[allow(non_camel_case_types)]
+    TRAFFIC_POSTPAID_BY_HOUR, //流量按小时后付费
+    #[allow(non_camel_case_types)]
+    BANDWIDTH_POSTPAID_BY_HOUR, //带宽按小时后付费
+    #[allow(non_camel_case_types)]
+    BANDWIDTH_PACKAGE, //带宽包用户
+}
+
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "PascalCase")]
-pub struct InquiryPriceRunInstancesResponse {
-    pub response: InquiryPriceRunInstancesResponseInner,
+pub struct InternetAccessible {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub internet_charge_type: Option<InternetChargeType>,
+    /// Mbps, 0 for no public network
+    pub internet_max_bandwidth_out: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_ip_assigned: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct VirtualPrivateCloud {
+    pub vpc_id: String,
+    pub subnet_id: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct TagSpecification {
+    /// `instance`, `disk`, ...
+    pub resource_type: String,
+    pub tags: Vec<Tag>,
+}
+
+/// bid of a SPOTPAID instance
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct InstanceMarketOptions {
+    /// always `spot`
+    pub market_type: String,
+    pub spot_options: SpotOptions,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SpotOptions {
+    /// max instance price per hour, bandwidth excluded, e.g. `"0.5"`
+    pub max_price: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spot_instance_type: Option<SpotInstanceType>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Display)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum SpotInstanceType {
+    /// the only one supported now
+    OneTime,
+}
+
+/// RunInstancesRequest, also the request of InquiryPriceRunInstances, see [`CVMInstanceBuilder::query_price`]
+///
+/// Nothing but the zone and instance type is set by default, the api defaults apply for the rest.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct RunInstancesRequest {
+    pub placement: Placement,
+    pub instance_type: InstanceType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance_charge_type: Option<InstanceChargeType>,
+    /// for SPOTPAID only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance_market_options: Option<InstanceMarketOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_disk: Option<SystemDisk>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub data_disks: Vec<DataDisk>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub virtual_private_cloud: Option<VirtualPrivateCloud>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub internet_accessible: Option<InternetAccessible>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance_count: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub login_settings: Option<LoginSettings>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub security_group_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host_name: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tag_specification: Vec<TagSpecification>,
+    /// idempotency key, at most 64 ascii characters
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_token: Option<String>,
+    /// base64 encoded, see [`Self::user_data`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_data: Option<String>,
+    /// check parameters, quota and stock only, success is reported as a `DryRunOperation` error
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dry_run: Option<bool>,
+}
+
+impl RunInstancesRequest {
+    pub fn new(zone: impl Into<String>, instance_type: InstanceType) -> Self {
+        Self {
+            placement: Placement {
+                zone: zone.into(),
+                project_id: None,
+            },
+            instance_type,
+            instance_charge_type: None,
+            instance_market_options: None,
+            image_id: None,
+            system_disk: None,
+            data_disks: vec![],
+            virtual_private_cloud: None,
+            internet_accessible: None,
+            instance_count: None,
+            instance_name: None,
+            login_settings: None,
+            security_group_ids: vec![],
+            host_name: None,
+            tag_specification: vec![],
+            client_token: None,
+            user_data: None,
+            dry_run: None,
+        }
+    }
+    pub fn zone(mut self, zone: impl Into<String>) -> Self {
+        self.placement.zone = zone.into();
+        self
+    }
+    pub fn instance_type(mut self, instance_type: InstanceType) -> Self {
+        self.instance_type = instance_type;
+        self
+    }
+    pub fn instance_charge_type(mut self, instance_charge_type: InstanceChargeType) -> Self {
+        self.instance_charge_type = Some(instance_charge_type);
+        self
+    }
+    /// SPOTPAID with a max price per hour, the instance is reclaimed once the market price goes above it
+    pub fn spot(mut self, max_price: f64, spot_instance_type: Option<SpotInstanceType>) -> Self {
+        self.instance_charge_type = Some(InstanceChargeType::SPOTPAID);
+        self.instance_market_options = Some(InstanceMarketOptions {
+            market_type: "spot".to_owned(),
+            spot_options: SpotOptions {
+                max_price: max_price.to_string(),
+                spot_instance_type,
+            },
+        });
+        self
+    }
+    pub fn image_id(mut self, image_id: impl Into<String>) -> Self {
+        self.image_id = Some(image_id.into());
+        self
+    }
+    pub fn system_disk(mut self, disk_type: Option<String>, disk_size: u32) -> Self {
+        self.system_disk = Some(SystemDisk {
+            disk_type,
+            disk_id: None,
+            disk_size,
+        });
+        self
+    }
+    pub fn data_disk(mut self, data_disk: DataDisk) -> Self {
+        self.data_disks.push(data_disk);
+        self
+    }
+    pub fn vpc(mut self, vpc_id: impl Into<String>, subnet_id: impl Into<String>) -> Self {
+        self.virtual_private_cloud = Some(VirtualPrivateCloud {
+            vpc_id: vpc_id.into(),
+            subnet_id: subnet_id.into(),
+        });
+        self
+    }
+    /// with a public ip
+    pub fn bandwidth(mut self, internet_charge_type: InternetChargeType, internet_max_bandwidth_out: u32) -> Self {
+        self.internet_accessible = Some(InternetAccessible {
+            internet_charge_type: Some(internet_charge_type),
+            internet_max_bandwidth_out,
+            public_ip_assigned: Some(true),
+        });
+        self
+    }
+    pub fn instance_count(mut self, instance_count: u32) -> Self {
+        self.instance_count = Some(instance_count);
+        self
+    }
+    pub fn instance_name(mut self, instance_name: impl Into<String>) -> Self {
+        self.instance_name = Some(instance_name.into());
+        self
+    }
+    pub fn host_name(mut self, host_name: impl Into<String>) -> Self {
+        self.host_name = Some(host_name.into());
+        self
+    }
+    pub fn login_settings(mut self, login_settings: LoginSettings) -> Self {
+        self.login_settings = Some(login_settings);
+        self
+    }
+    pub fn key_ids(self, key_ids: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.login_settings(LoginSettings::key_ids(key_ids))
+    }
+    pub fn security_group_ids(mut self, security_group_ids: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.security_group_ids = security_group_ids.into_iter().map(Into::into).collect();
+        self
+    }
+    /// tag the instance
+    pub fn tag(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        let tag = Tag {
+            key: key.into(),
+            value: value.into(),
+        };
+        match self
+            .tag_specification
+            .iter_mut()
+            .find(|t| t.resource_type == "instance")
+        {
+            Some(spec) => spec.tags.push(tag),
+            None => self.tag_specification.push(TagSpecification {
+                resource_type: "instance".to_owned(),
+                tags: vec![tag],
+            }),
+        }
+        self
+    }
+    pub fn client_token(mut self, client_token: impl Into<String>) -> Self {
+        self.client_token = Some(client_token.into());
+        self
+    }
+    /// cloud-init script, at most 16KB before encoding
+    pub fn user_data(mut self, user_data: &str) -> Self {
+        self.user_data = Some(BASE64_STANDARD.encode(user_data));
+        self
+    }
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = Some(dry_run);
+        self
+    }
+
+    /// InquiryPriceRunInstances takes the same parameters but these
+    fn inquiry_price(&self) -> Self {
+        Self {
+            user_data: None,
+            dry_run: None,
+            ..self.clone()
+        }
+    }
 }
+
+/// InquiryPriceRunInstancesResponse
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "PascalCase")]
-pub struct InquiryPriceRunInstancesResponseInner {
+pub struct InquiryPriceRunInstancesResponse {
     pub price: Price,
 }
 #[derive(Debug, Deserialize)]
@@ -83,168 +543,470 @@ pub struct PriceDetail {
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct RunInstancesResponse {
-    pub response: RunInstancesResponseInner,
+    pub instance_id_set: Vec<String>,
 }
 
-#[derive(Debug, Deserialize)]
+/// TerminateInstancesRequest
+#[derive(Debug, Serialize)]
 #[serde(rename_all = "PascalCase")]
-pub struct RunInstancesResponseInner {
-    pub instance_id_set: Vec<String>,
+pub struct TerminateInstancesRequest {
+    pub instance_ids: Vec<String>,
 }
 
-const DESCRIBE_INSTANCES: &str = "DescribeInstances";
-const INQUIRY_PRICE_RUN_INSTANCES: &str = "InquiryPriceRunInstances";
-const RUN_INSTANCES: &str = "RunInstances";
-const TERMINATE_INSTANCES: &str = "TerminateInstances";
+/// StartInstancesRequest
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct StartInstancesRequest {
+    pub instance_ids: Vec<String>,
+}
 
-impl CVMInstanceBuilder {
-    pub fn new(client: Arc<TencentCloudBaseClient>) -> Self {
+/// StopInstancesRequest
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct StopInstancesRequest {
+    pub instance_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_type: Option<StopType>,
+    /// only for POSTPAID_BY_HOUR instances, KEEP_CHARGING by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_charging_mode: Option<StopChargingMode>,
+}
+
+/// RebootInstancesRequest
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct RebootInstancesRequest {
+    pub instance_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_type: Option<StopType>,
+}
+
+/// how to log in after the system disk is installed
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct LoginSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub key_ids: Vec<String>,
+    /// keep the login settings baked in a custom image, `"TRUE"` / `"FALSE"`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_image_login: Option<String>,
+}
+
+impl LoginSettings {
+    pub fn key_ids(key_ids: impl IntoIterator<Item = impl Into<String>>) -> Self {
         Self {
-            client,
-            service_name: "cvm".into(),
-            version: "2017-03-12".into(),
+            key_ids: key_ids.into_iter().map(Into::into).collect(),
+            ..Default::default()
         }
     }
-    pub async fn describe_instance(&self, region: &Region) -> anyhow::Result<DescribeInstancesResponse> {
-        let resp = self
-            .client
-            .post(&self.service_name, &self.version)
-            .header(ACTION_HEADER, DESCRIBE_INSTANCES)
-            .header(REGION_HEADER, region.to_string())
-            .json(&json!({}))
-            .send()
-            .await?;
+    pub fn keep_image_login() -> Self {
+        Self {
+            keep_image_login: Some("TRUE".to_owned()),
+            ..Default::default()
+        }
+    }
+}
 
-        match resp.status() {
-            StatusCode::OK => Ok(resp.json().await?),
-            rest => Err(anyhow::anyhow!("err get code {rest}, msg {}", resp.text().await?)),
+/// ResetInstanceRequest
+///
+/// Reinstalls the system disk, the instance id, ips and data disks are kept.
+/// The instance must be RUNNING or STOPPED.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ResetInstanceRequest {
+    pub instance_id: String,
+    /// the current image if not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub login_settings: Option<LoginSettings>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host_name: Option<String>,
+}
+
+impl ResetInstanceRequest {
+    pub fn new(instance_id: impl Into<String>) -> Self {
+        Self {
+            instance_id: instance_id.into(),
+            image_id: None,
+            login_settings: None,
+            host_name: None,
         }
     }
+    pub fn image_id(mut self, image_id: impl Into<String>) -> Self {
+        self.image_id = Some(image_id.into());
+        self
+    }
+    pub fn login_settings(mut self, login_settings: LoginSettings) -> Self {
+        self.login_settings = Some(login_settings);
+        self
+    }
+    pub fn host_name(mut self, host_name: impl Into<String>) -> Self {
+        self.host_name = Some(host_name.into());
+        self
+    }
+}
 
-    /// set default SPOTPAID/Ubuntu2204/20GB disk
-    pub async fn query_price(
+impl_action!(DescribeInstances: SERVICE, VERSION, DescribeInstancesRequest => DescribeInstancesResponse);
+impl_action!(InquiryPriceRunInstances: SERVICE, VERSION, RunInstancesRequest => InquiryPriceRunInstancesResponse);
+impl_action!(RunInstances: SERVICE, VERSION, RunInstancesRequest => RunInstancesResponse);
+impl_action!(TerminateInstances: SERVICE, VERSION, TerminateInstancesRequest => EmptyResponse);
+impl_action!(StartInstances: SERVICE, VERSION, StartInstancesRequest => EmptyResponse);
+impl_action!(StopInstances: SERVICE, VERSION, StopInstancesRequest => EmptyResponse);
+impl_action!(RebootInstances: SERVICE, VERSION, RebootInstancesRequest => EmptyResponse);
+impl_action!(ResetInstance: SERVICE, VERSION, ResetInstanceRequest => EmptyResponse);
+
+impl CVMInstanceBuilder {
+    pub fn new(client: Arc<TencentCloudBaseClient>) -> Self {
+        Self { client }
+    }
+    /// one page of DescribeInstances, see [`Self::describe_instances_stream`] for all of them
+    pub async fn describe_instances(
         &self,
         region: &Region,
-        zone: &str,
-        instance_type: &InstanceType,
-    ) -> anyhow::Result<Price> {
-        let resp = self
+        request: &DescribeInstancesRequest,
+    ) -> Result<DescribeInstancesResponse> {
+        self.client.call::<DescribeInstances>(region, request).await
+    }
+
+    pub async fn describe_instance(&self, region: &Region, instance_id: &str) -> Result<Option<Instance>> {
+        let request = DescribeInstancesRequest::new().instance_ids([instance_id]);
+        let body = self.describe_instances(region, &request).await?;
+        Ok(body.instance_set.into_iter().find(|i| i.instance_id == instance_id))
+    }
+
+    /// walk every page starting from `request.offset`, `request.limit` is used as page size
+    pub fn describe_instances_stream(
+        &self,
+        region: &Region,
+        request: DescribeInstancesRequest,
+    ) -> BoxStream<'static, Result<Instance>> {
+        paged_stream::<DescribeInstances>(self.client.clone(), region, request)
+    }
+
+    /// price of launching `request`
+    pub async fn query_price(&self, region: &Region, request: &RunInstancesRequest) -> Result<Price> {
+        let body = self
             .client
-            .post(&self.service_name, &self.version)
-            .header(ACTION_HEADER, INQUIRY_PRICE_RUN_INSTANCES)
-            .header(REGION_HEADER, region.to_string())
-            .json(&json!({
-                "InstanceChargeType": "SPOTPAID",
-                "ImageId": "img-487zeit5",
-                "Placement": {
-                    "Zone": zone
-                },
-                "InstanceType": instance_type.to_string(),
-                "InstanceCount": 1,
-                "SystemDisk": {
-                    "DiskSize": 20,
+            .call::<InquiryPriceRunInstances>(region, &request.inquiry_price())
+            .await?;
+        debug!("body: {body:?}");
+        Ok(body.price)
+    }
+
+    /// ids of the launched instances, `request.instance_count` of them.
+    /// a `ClientToken` is generated if not set, the call is retried and must not launch twice.
+    pub async fn run_instances(&self, region: &Region, request: &RunInstancesRequest) -> Result<Vec<String>> {
+        let mut request = request.clone();
+        request.client_token.get_or_insert_with(client_token);
+        let body = self.client.call::<RunInstances>(region, &request).await?;
+        debug!("body: {body:?}");
+        Ok(body.instance_id_set)
+    }
+
+    pub async fn run_instance(&self, region: &Region, request: &RunInstancesRequest) -> Result<String> {
+        self.run_instances(region, request)
+            .await?
+            .into_iter()
+            .nth(0)
+            .ok_or_else(|| serde::de::Error::custom("panic!! response missing id ???"))
+            .map_err(TencentCloudError::Decode)
+    }
+
+    pub async fn terminate_instance(&self, region: &Region, instance_id: &str) -> Result<()> {
+        let body = self
+            .client
+            .call::<TerminateInstances>(
+                region,
+                &TerminateInstancesRequest {
+                    instance_ids: vec![instance_id.to_owned()],
                 },
-                "InternetAccessible": {
-                    "InternetChargeType": "TRAFFIC_POSTPAID_BY_HOUR",
-                    "InternetMaxBandwidthOut": 10,
-                    "PublicIpAssigned": true
-                }
-            }))
-            .send()
+            )
             .await?;
+        debug!("body: {body:?}");
+        Ok(())
+    }
 
-        match resp.status() {
-            StatusCode::OK => {
-                // let body = resp.text().await?;
-                // debug!("body: {body:?}");
-                // let body: InquiryPriceRunInstancesResponse = serde_json::from_str(&body)?;
-                // debug!("body: {body:?}");
-                let body: InquiryPriceRunInstancesResponse = resp.json().await?;
-                debug!("body: {body:?}");
-                Ok(body.response.price)
-            }
-            rest => Err(anyhow::anyhow!("err get code {rest}, msg {}", resp.text().await?)),
-        }
+    /// STOPPED -> STARTING -> RUNNING
+    pub async fn start_instance(&self, region: &Region, instance_id: &str) -> Result<()> {
+        let request = StartInstancesRequest {
+            instance_ids: vec![instance_id.to_owned()],
+        };
+        let body = self.client.call::<StartInstances>(region, &request).await?;
+        debug!("body: {body:?}");
+        Ok(())
     }
 
-    /// set default SPOTPAID/Ubuntu2204/20GB disk
-    pub async fn run_instance(
+    /// RUNNING -> STOPPING -> STOPPED, keeps the disks and the instance id
+    pub async fn stop_instance(
         &self,
         region: &Region,
-        zone: &str,
-        instance_type: &InstanceType,
-        key_ids: &[String],
-        security_group: Vec<String>,
-    ) -> anyhow::Result<String> {
-        let mut body = json!({
+        instance_id: &str,
+        stop_type: Option<StopType>,
+        stop_charging_mode: Option<StopChargingMode>,
+    ) -> Result<()> {
+        let request = StopInstancesRequest {
+            instance_ids: vec![instance_id.to_owned()],
+            stop_type,
+            stop_charging_mode,
+        };
+        let body = self.client.call::<StopInstances>(region, &request).await?;
+        debug!("body: {body:?}");
+        Ok(())
+    }
+
+    /// RUNNING -> REBOOTING -> RUNNING
+    pub async fn reboot_instance(&self, region: &Region, instance_id: &str, stop_type: Option<StopType>) -> Result<()> {
+        let request = RebootInstancesRequest {
+            instance_ids: vec![instance_id.to_owned()],
+            stop_type,
+        };
+        let body = self.client.call::<RebootInstances>(region, &request).await?;
+        debug!("body: {body:?}");
+        Ok(())
+    }
+
+    /// reinstall the system disk, returns the request id to match `latest_operation_request_id` of the instance
+    pub async fn reset_instance(&self, region: &Region, request: &ResetInstanceRequest) -> Result<String> {
+        let body = self.client.call::<ResetInstance>(region, request).await?;
+        debug!("body: {body:?}");
+        Ok(body.request_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::TryStreamExt;
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        client::{TencentCloudClient, mock::MockServer},
+        config::{ClientConfig, Endpoint},
+    };
+
+    #[test]
+    fn test_deserialize_instance() {
+        let instance: Instance = serde_json::from_value(json!({
+            "Placement": {"Zone": "ap-nanjing-1", "ProjectId": 0, "HostId": null},
+            "InstanceId": "ins-1",
+            "InstanceType": "SA2.MEDIUM2",
+            "CPU": 2,
+            "Memory": 2,
+            "RestrictState": "NORMAL",
+            "InstanceName": "palworld",
             "InstanceChargeType": "SPOTPAID",
+            "SystemDisk": {"DiskType": "CLOUD_PREMIUM", "DiskId": "disk-1", "DiskSize": 20},
+            "DataDisks": null,
+            "PrivateIpAddresses": ["10.0.0.2"],
+            "PublicIpAddresses": null,
             "ImageId": "img-487zeit5",
-            "Placement": {
-                "Zone": zone
-            },
-            "InstanceType": instance_type.to_string(),
-            "InstanceCount": 1,
-            "SystemDisk": {
-                "DiskSize": 20,
-            },
+            "CreatedTime": "2024-01-25T12:00:00Z",
+            "InstanceState": "STOPPED",
+            "Tags": [{"Key": "app", "Value": "palworld"}],
+            "LatestOperation": "StopInstances",
+            "LatestOperationState": "SUCCESS",
+            "LatestOperationRequestId": "req-1",
+            "StopChargingMode": "STOP_CHARGING",
+        }))
+        .unwrap();
+        assert_eq!(instance.placement.zone, "ap-nanjing-1");
+        assert_eq!(instance.system_disk.disk_type.as_deref(), Some("CLOUD_PREMIUM"));
+        assert_eq!(instance.instance_charge_type, InstanceChargeType::SPOTPAID);
+        assert_eq!(instance.latest_operation_state, Some(LatestOperationState::SUCCESS));
+        assert_eq!(instance.tag("app"), Some("palworld"));
+        assert_eq!(instance.public_ip(), None);
+        assert_eq!(instance.stop_charging_mode, Some(StopChargingMode::StopCharging));
+        assert_eq!(
+            InstanceState::after(instance.latest_operation.as_deref().unwrap()),
+            Some(instance.instance_state)
+        );
+
+        let instance: Instance = serde_json::from_value(json!({
+            "Placement": {"Zone": "ap-nanjing-1"},
+            "InstanceId": "ins-2",
+            "InstanceType": "SA2.MEDIUM2",
+            "CPU": 2,
+            "Memory": 2,
+            "InstanceName": "palworld",
+            "InstanceChargeType": "UNDEFINED",
+            "SystemDisk": {"DiskSize": 20},
+            "PrivateIpAddresses": null,
+            "ImageId": "img-487zeit5",
+            "InstanceState": "RUNNING",
+            "Tags": null,
+        }))
+        .unwrap();
+        assert_eq!(instance.instance_charge_type, InstanceChargeType::UNKNOWN);
+        assert!(instance.tags.is_empty() && instance.private_ip_addresses.is_empty());
+    }
+
+    #[test]
+    fn test_run_instances_request() {
+        let request = RunInstancesRequest::new("ap-nanjing-1", InstanceType::SA2Medium2)
+            .instance_charge_type(InstanceChargeType::SPOTPAID)
+            .image_id("img-487zeit5")
+            .system_disk(None, 20)
+            .bandwidth(InternetChargeType::TRAFFIC_POSTPAID_BY_HOUR, 10)
+            .instance_name("psm-test")
+            .tag("app", "palworld")
+            .tag("server", "test")
+            .key_ids(["skey-1"])
+            .client_token("token-1")
+            .user_data("#!/bin/sh\necho hi\n")
+            .dry_run(true);
+        let expected = json!({
+            "Placement": {"Zone": "ap-nanjing-1"},
+            "InstanceType": "SA2.MEDIUM2",
+            "InstanceChargeType": "SPOTPAID",
+            "ImageId": "img-487zeit5",
+            "SystemDisk": {"DiskSize": 20},
             "InternetAccessible": {
                 "InternetChargeType": "TRAFFIC_POSTPAID_BY_HOUR",
                 "InternetMaxBandwidthOut": 10,
-                "PublicIpAssigned": true
-            }
+                "PublicIpAssigned": true,
+            },
+            "InstanceName": "psm-test",
+            "LoginSettings": {"KeyIds": ["skey-1"]},
+            "TagSpecification": [{
+                "ResourceType": "instance",
+                "Tags": [{"Key": "app", "Value": "palworld"}, {"Key": "server", "Value": "test"}],
+            }],
+            "ClientToken": "token-1",
         });
-        if !key_ids.is_empty() {
-            body["LoginSettings"] = json!({
-                "KeyIds": key_ids
-            });
-        }
-        if !security_group.is_empty() {
-            body["SecurityGroupIds"] = security_group.into();
-        }
-        let resp = self
-            .client
-            .post(&self.service_name, &self.version)
-            .header(ACTION_HEADER, RUN_INSTANCES)
-            .header(REGION_HEADER, region.to_string())
-            .json(&body)
-            .send()
-            .await?;
+        let mut run = expected.clone();
+        run["UserData"] = json!("IyEvYmluL3NoCmVjaG8gaGkK");
+        run["DryRun"] = json!(true);
+        assert_eq!(serde_json::to_value(&request).unwrap(), run);
+        assert_eq!(serde_json::to_value(request.inquiry_price()).unwrap(), expected);
 
-        match resp.status() {
-            StatusCode::OK => {
-                let body = resp.text().await?;
-                debug!("body: {body:?}");
-                let body: RunInstancesResponse = serde_json::from_str(&body)?;
-                debug!("body: {body:?}");
-                body.response
-                    .instance_id_set
-                    .into_iter()
-                    .nth(0)
-                    .ok_or(anyhow::anyhow!("panic!! response missing id ???"))
-            }
-            rest => Err(anyhow::anyhow!("err get code {rest}, msg {}", resp.text().await?)),
-        }
+        let request = RunInstancesRequest::new("ap-nanjing-1", InstanceType::SA2Medium2)
+            .spot(0.35, Some(SpotInstanceType::OneTime));
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({
+                "Placement": {"Zone": "ap-nanjing-1"},
+                "InstanceType": "SA2.MEDIUM2",
+                "InstanceChargeType": "SPOTPAID",
+                "InstanceMarketOptions": {
+                    "MarketType": "spot",
+                    "SpotOptions": {"MaxPrice": "0.35", "SpotInstanceType": "one-time"},
+                },
+            })
+        );
     }
 
-    pub async fn terminate_instance(&self, region: &Region, instance_id: &str) -> anyhow::Result<()> {
-        let resp = self
-            .client
-            .post(&self.service_name, &self.version)
-            .header(ACTION_HEADER, TERMINATE_INSTANCES)
-            .header(REGION_HEADER, region.to_string())
-            .json(&json!({
-                "InstanceIds": [instance_id]
-            }))
-            .send()
-            .await?;
+    #[test]
+    fn test_instance_operation_requests() {
+        let request = StopInstancesRequest {
+            instance_ids: vec!["ins-1".to_owned()],
+            stop_type: Some(StopType::SoftFirst),
+            stop_charging_mode: Some(StopChargingMode::StopCharging),
+        };
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({"InstanceIds": ["ins-1"], "StopType": "SOFT_FIRST", "StopChargingMode": "STOP_CHARGING"})
+        );
+        assert!(InstanceState::STOPPING.is_transitional());
 
-        match resp.status() {
-            StatusCode::OK => {
-                let body = resp.text().await;
-                debug!("body: {body:?}");
-                Ok(())
-            }
-            rest => Err(anyhow::anyhow!("err get code {rest}, msg {}", resp.text().await?)),
-        }
+        let request = ResetInstanceRequest::new("ins-1")
+            .image_id("img-1")
+            .login_settings(LoginSettings::key_ids(["skey-1"]));
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({"InstanceId": "ins-1", "ImageId": "img-1", "LoginSettings": {"KeyIds": ["skey-1"]}})
+        );
+        assert!(!InstanceState::STOPPED.is_transitional());
+    }
+
+    #[test]
+    fn test_describe_instances_request() {
+        let request = DescribeInstancesRequest::new()
+            .zone("ap-nanjing-1")
+            .tag("app", "palworld")
+            .instance_state(InstanceState::LAUNCH_FAILED)
+            .limit(100);
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({
+                "Filters": [
+                    {"Name": "zone", "Values": ["ap-nanjing-1"]},
+                    {"Name": "tag:app", "Values": ["palworld"]},
+                    {"Name": "instance-state", "Values": ["LAUNCH_FAILED"]},
+                ],
+                "Limit": 100,
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(DescribeInstancesRequest::new().instance_ids(["ins-1"])).unwrap(),
+            json!({"InstanceIds": ["ins-1"]})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_describe_instances_stream() {
+        let instance = |id: &str| {
+            json!({
+                "InstanceId": id, "InstanceName": "psm-test", "InstanceState": "RUNNING",
+                "Placement": {"Zone": "ap-nanjing-1"}, "InstanceType": "SA2.MEDIUM2", "CPU": 2, "Memory": 2,
+                "InstanceChargeType": "SPOTPAID", "ImageId": "img-487zeit5", "SystemDisk": {"DiskSize": 20},
+            })
+        };
+        let server = MockServer::start(vec![
+            json!({"Response": {"TotalCount": 3, "InstanceSet": [instance("ins-1"), instance("ins-2")], "RequestId": "req-1"}}),
+            json!({"Response": {"TotalCount": 3, "InstanceSet": [instance("ins-3")], "RequestId": "req-2"}}),
+        ])
+        .await;
+        let config = ClientConfig::new("ak", "sk").endpoint(Endpoint::Custom(server.url.clone()));
+        let client = TencentCloudClient::new(&config).unwrap();
+
+        let request = DescribeInstancesRequest::new().tag("app", "palworld").limit(2);
+        let instances: Vec<Instance> = client
+            .cvm()
+            .instances()
+            .describe_instances_stream(&Region::Nanjing, request)
+            .try_collect()
+            .await
+            .unwrap();
+        let ids = instances.iter().map(|i| i.instance_id.as_str()).collect::<Vec<_>>();
+        assert_eq!(ids, ["ins-1", "ins-2", "ins-3"]);
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(
+            (&requests[0].json()["Offset"], &requests[0].json()["Limit"]),
+            (&json!(0), &json!(2))
+        );
+        assert_eq!(
+            (&requests[1].json()["Offset"], &requests[1].json()["Limit"]),
+            (&json!(2), &json!(2))
+        );
+        assert_eq!(requests[1].json()["Filters"][0]["Name"], "tag:app");
+
+        // an empty page ends the walk even if the total count says otherwise
+        let server = MockServer::start(vec![
+            json!({"Response": {"TotalCount": 5, "InstanceSet": [instance("ins-1")], "RequestId": "req-1"}}),
+            json!({"Response": {"TotalCount": 5, "InstanceSet": [], "RequestId": "req-2"}}),
+        ])
+        .await;
+        let config = ClientConfig::new("ak", "sk").endpoint(Endpoint::Custom(server.url.clone()));
+        let client = TencentCloudClient::new(&config).unwrap();
+
+        let instances: Vec<Instance> = client
+            .cvm()
+            .instances()
+            .describe_instances_stream(&Region::Nanjing, DescribeInstancesRequest::new().offset(1))
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(instances.len(), 1);
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(
+            (&requests[0].json()["Offset"], &requests[0].json()["Limit"]),
+            (&json!(1), &json!(100))
+        );
+        assert_eq!(requests[1].json()["Offset"], 2);
     }
 }
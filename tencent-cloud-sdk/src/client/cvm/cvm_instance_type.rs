@@ -0,0 +1,195 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    client::{
+        action::{Filter, impl_action},
+        cvm::cvm_instance::InstanceChargeType,
+    },
+    constant::{InstanceType, Region},
+    error::Result,
+};
+
+use super::*;
+
+pub struct CVMInstanceTypeBuilder {
+    client: Arc<TencentCloudBaseClient>,
+}
+
+/// DescribeZoneInstanceConfigInfosRequest
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DescribeZoneInstanceConfigInfosRequest {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub filters: Vec<Filter>,
+}
+
+impl DescribeZoneInstanceConfigInfosRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+    pub fn zone(self, zone: &str) -> Self {
+        self.filter(Filter::new("zone", [zone]))
+    }
+    /// e.g. `S5`, `SA2`
+    pub fn instance_family(self, family: &str) -> Self {
+        self.filter(Filter::new("instance-family", [family]))
+    }
+    pub fn instance_type(self, instance_type: &str) -> Self {
+        self.filter(Filter::new("instance-type", [instance_type]))
+    }
+    /// POSTPAID_BY_HOUR if not set
+    pub fn instance_charge_type(self, charge_type: InstanceChargeType) -> Self {
+        self.filter(Filter::new("instance-charge-type", [charge_type.to_string()]))
+    }
+}
+
+/// DescribeZoneInstanceConfigInfosResponse
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DescribeZoneInstanceConfigInfosResponse {
+    pub instance_type_quota_set: Vec<InstanceTypeQuotaItem>,
+}
+
+/// an instance type sold in a zone
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct InstanceTypeQuotaItem {
+    pub zone: String,
+    pub instance_type: InstanceType,
+    pub instance_charge_type: InstanceChargeType,
+    /// e.g. `SA2`
+    pub instance_family: String,
+    /// e.g. `标准型SA2`
+    #[serde(default)]
+    pub type_name: String,
+    pub cpu: u32,
+    /// GB
+    pub memory: u32,
+    #[serde(default)]
+    pub gpu: f64,
+    #[serde(default)]
+    pub fpga: u32,
+    pub status: InstanceTypeStatus,
+    /// EnoughStock / NormalStock / UnderStock / WithoutStock
+    pub status_category: Option<String>,
+    pub sold_out_reason: Option<String>,
+    pub cpu_type: Option<String>,
+    pub frequency: Option<String>,
+}
+
+impl InstanceTypeQuotaItem {
+    pub fn is_selling(&self) -> bool {
+        self.status == InstanceTypeStatus::SELL
+    }
+
+    /// spot capacity is only offered for SPOTPAID items on sale
+    pub fn is_spot_available(&self) -> bool {
+        self.instance_charge_type == InstanceChargeType::SPOTPAID && self.is_selling()
+    }
+
+    /// no gpu or fpga
+    pub fn is_general(&self) -> bool {
+        self.gpu == 0.0 && self.fpga == 0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub enum InstanceTypeStatus {
+    SELL,
+    #[allow(non_camel_case_types)]
+    SOLD_OUT,
+    #[serde(other)]
+    UNKNOWN,
+}
+
+impl_action!(DescribeZoneInstanceConfigInfos: SERVICE, VERSION, DescribeZoneInstanceConfigInfosRequest => DescribeZoneInstanceConfigInfosResponse);
+
+impl CVMInstanceTypeBuilder {
+    pub fn new(client: Arc<TencentCloudBaseClient>) -> Self {
+        Self { client }
+    }
+
+    pub async fn describe_zone_instance_config_infos(
+        &self,
+        region: &Region,
+        request: &DescribeZoneInstanceConfigInfosRequest,
+    ) -> Result<Vec<InstanceTypeQuotaItem>> {
+        let body = self
+            .client
+            .call::<DescribeZoneInstanceConfigInfos>(region, request)
+            .await?;
+        Ok(body.instance_type_quota_set)
+    }
+
+    /// every general purpose type with `cpu` cores and `memory` GB sold as spot in `region`
+    pub async fn spot_instance_types(
+        &self,
+        region: &Region,
+        cpu: u32,
+        memory: u32,
+    ) -> Result<Vec<InstanceTypeQuotaItem>> {
+        let request = DescribeZoneInstanceConfigInfosRequest::new().instance_charge_type(InstanceChargeType::SPOTPAID);
+        let items = self.describe_zone_instance_config_infos(region, &request).await?;
+        Ok(items
+            .into_iter()
+            .filter(|i| i.is_spot_available() && i.is_general() && i.cpu == cpu && i.memory == memory)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_deserialize_quota_item() {
+        let items: Vec<InstanceTypeQuotaItem> = serde_json::from_value(json!([
+            {
+                "Zone": "ap-nanjing-1",
+                "InstanceType": "SA5.LARGE16",
+                "InstanceChargeType": "SPOTPAID",
+                "NetworkCard": 0,
+                "Externals": {},
+                "Cpu": 4,
+                "Memory": 16,
+                "InstanceFamily": "SA5",
+                "TypeName": "标准型SA5",
+                "LocalDiskTypeList": [],
+                "Status": "SELL",
+                "Price": {"UnitPrice": 0.4, "ChargeUnit": "HOUR"},
+                "SoldOutReason": "",
+                "InstanceBandwidth": 1.5,
+                "InstancePps": 30,
+                "StorageBlockAmount": 0,
+                "CpuType": "AMD EPYC™ Bergamo",
+                "Gpu": 0,
+                "Fpga": 0,
+                "Remark": "",
+                "GpuCount": 0,
+                "Frequency": "-/3.1GHz",
+                "StatusCategory": "EnoughStock",
+            },
+            {
+                "Zone": "ap-nanjing-2",
+                "InstanceType": "GN7.LARGE20",
+                "InstanceChargeType": "SPOTPAID",
+                "Cpu": 4,
+                "Memory": 20,
+                "InstanceFamily": "GN7",
+                "Status": "SOLD_OUT",
+                "Gpu": 0.25,
+            },
+        ]))
+        .unwrap();
+        assert!(items[0].is_spot_available() && items[0].is_general());
+        assert_eq!(items[0].instance_type, InstanceType::SA5Large16);
+        assert_eq!(items[0].status_category.as_deref(), Some("EnoughStock"));
+        assert!(!items[1].is_spot_available() && !items[1].is_general());
+    }
+}
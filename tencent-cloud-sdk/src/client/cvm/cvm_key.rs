@@ -1,28 +1,35 @@
-use reqwest::StatusCode;
-use serde::Deserialize;
-use serde_json::json;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
 
 use crate::{
-    client::{ACTION_HEADER, REGION_HEADER},
+    client::action::{EmptyResponse, Filter, impl_action},
     constant::Region,
+    error::Result,
 };
 
 use super::*;
 pub struct CVMKeyBuilder {
     client: Arc<TencentCloudBaseClient>,
-    service_name: String,
-    version: String,
 }
 
-#[derive(Debug, Deserialize)]
+/// DescribeKeyPairsRequest
+#[derive(Debug, Default, Serialize)]
 #[serde(rename_all = "PascalCase")]
-pub struct DescribeKeyPairsReponse {
-    pub response: DescribeKeyPairsResponseInner,
+pub struct DescribeKeyPairsRequest {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub key_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub filters: Vec<Filter>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u32>,
+    /// 20 by default, at most 100
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "PascalCase")]
-pub struct DescribeKeyPairsResponseInner {
+pub struct DescribeKeyPairsResponse {
     #[allow(unused)]
     pub total_count: usize,
     pub key_pair_set: Vec<KeyPair>,
@@ -34,38 +41,169 @@ pub struct KeyPair {
     pub key_id: String,
     pub key_name: String,
     pub public_key: String,
+    /// only returned by CreateKeyPair, never stored by tencent cloud
+    #[serde(default)]
+    pub private_key: Option<String>,
+    #[serde(default)]
+    pub associated_instance_ids: Vec<String>,
     pub created_time: String,
 }
 
-const DESCRIBE_KEY_PAIRS: &str = "DescribeKeyPairs";
+impl KeyPair {
+    /// same key type and data as an openssh public key line, the comment is ignored
+    pub fn matches(&self, public_key: &str) -> bool {
+        self.public_key
+            .split_whitespace()
+            .take(2)
+            .eq(public_key.split_whitespace().take(2))
+    }
+}
+
+/// CreateKeyPairRequest
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CreateKeyPairRequest {
+    pub key_name: String,
+    pub project_id: i64,
+}
+
+/// CreateKeyPairResponse
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CreateKeyPairResponse {
+    pub key_pair: KeyPair,
+}
+
+/// ImportKeyPairRequest
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ImportKeyPairRequest {
+    pub key_name: String,
+    pub project_id: i64,
+    /// openssh public key line, e.g. `ssh-ed25519 AAAA... user@host`
+    pub public_key: String,
+}
+
+/// ImportKeyPairResponse
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ImportKeyPairResponse {
+    pub key_id: String,
+}
+
+/// DeleteKeyPairsRequest
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DeleteKeyPairsRequest {
+    pub key_ids: Vec<String>,
+}
+
+/// AssociateInstancesKeyPairsRequest
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AssociateInstancesKeyPairsRequest {
+    pub instance_ids: Vec<String>,
+    pub key_ids: Vec<String>,
+    /// stop running instances first, the call fails on them otherwise
+    pub force_stop: bool,
+}
+
+impl_action!(DescribeKeyPairs: SERVICE, VERSION, DescribeKeyPairsRequest => DescribeKeyPairsResponse);
+impl_action!(CreateKeyPair: SERVICE, VERSION, CreateKeyPairRequest => CreateKeyPairResponse, IDEMPOTENT = false, SENSITIVE = true);
+impl_action!(ImportKeyPair: SERVICE, VERSION, ImportKeyPairRequest => ImportKeyPairResponse, IDEMPOTENT = false);
+impl_action!(DeleteKeyPairs: SERVICE, VERSION, DeleteKeyPairsRequest => EmptyResponse);
+impl_action!(AssociateInstancesKeyPairs: SERVICE, VERSION, AssociateInstancesKeyPairsRequest => EmptyResponse);
 
 impl CVMKeyBuilder {
     pub fn new(client: Arc<TencentCloudBaseClient>) -> Self {
-        Self {
-            client,
-            service_name: "cvm".into(),
-            version: "2017-03-12".into(),
-        }
+        Self { client }
+    }
+    /// the first 100 key pairs of the account
+    pub async fn describe_key_pairs(&self, region: &Region) -> Result<Vec<KeyPair>> {
+        let request = DescribeKeyPairsRequest {
+            limit: Some(100),
+            ..Default::default()
+        };
+        let body = self.client.call::<DescribeKeyPairs>(region, &request).await?;
+        Ok(body.key_pair_set)
     }
-    pub async fn describe_key_pairs(&self, region: &Region) -> anyhow::Result<Vec<KeyPair>> {
-        let resp = self
+    pub async fn find_key_pair(&self, region: &Region, key_name: &str) -> Result<Option<KeyPair>> {
+        let request = DescribeKeyPairsRequest {
+            filters: vec![Filter::new("key-name", [key_name])],
+            ..Default::default()
+        };
+        let body = self.client.call::<DescribeKeyPairs>(region, &request).await?;
+        Ok(body.key_pair_set.into_iter().find(|k| k.key_name == key_name))
+    }
+    /// a new key pair, the private key is only available in this response
+    pub async fn create_key_pair(&self, region: &Region, key_name: &str) -> Result<KeyPair> {
+        let request = CreateKeyPairRequest {
+            key_name: key_name.to_owned(),
+            project_id: 0,
+        };
+        let body = self.client.call::<CreateKeyPair>(region, &request).await?;
+        Ok(body.key_pair)
+    }
+    /// return the key id
+    pub async fn import_key_pair(&self, region: &Region, key_name: &str, public_key: &str) -> Result<String> {
+        let request = ImportKeyPairRequest {
+            key_name: key_name.to_owned(),
+            project_id: 0,
+            public_key: public_key.trim().to_owned(),
+        };
+        let body = self.client.call::<ImportKeyPair>(region, &request).await?;
+        Ok(body.key_id)
+    }
+    /// key pairs still bound to instances can't be deleted
+    pub async fn delete_key_pairs(&self, region: &Region, key_ids: Vec<String>) -> Result<()> {
+        let body = self
             .client
-            .post(&self.service_name, &self.version)
-            .header(ACTION_HEADER, DESCRIBE_KEY_PAIRS)
-            .header(REGION_HEADER, region.to_string())
-            .json(&json!({}))
-            .send()
+            .call::<DeleteKeyPairs>(region, &DeleteKeyPairsRequest { key_ids })
             .await?;
-        match resp.status() {
-            StatusCode::OK => {
-                let body: DescribeKeyPairsReponse = resp.json().await?;
-                // println!("DescribeZoneResponse body: {body:?}");
-                Ok(body.response.key_pair_set)
-            }
-            rest => Err(anyhow::anyhow!(
-                "err get code {rest}, msg {}",
-                resp.text().await?
-            )),
-        }
+        debug!("body: {body:?}");
+        Ok(())
+    }
+    /// the login key pairs of the instances are replaced
+    pub async fn associate_instances_key_pairs(
+        &self,
+        region: &Region,
+        instance_ids: Vec<String>,
+        key_ids: Vec<String>,
+        force_stop: bool,
+    ) -> Result<()> {
+        let request = AssociateInstancesKeyPairsRequest {
+            instance_ids,
+            key_ids,
+            force_stop,
+        };
+        let body = self.client.call::<AssociateInstancesKeyPairs>(region, &request).await?;
+        debug!("body: {body:?}");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_key_pair_matches() {
+        let key: KeyPair = serde_json::from_value(json!({
+            "KeyId": "skey-1",
+            "KeyName": "psm_cli",
+            "ProjectId": 0,
+            "Description": "",
+            "PublicKey": "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIK0 skey_1",
+            "PrivateKey": "",
+            "AssociatedInstanceIds": ["ins-1"],
+            "CreatedTime": "2024-01-25T12:00:00Z",
+            "Tags": [],
+        }))
+        .unwrap();
+        assert_eq!(key.associated_instance_ids, vec!["ins-1"]);
+        assert!(key.matches("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIK0 me@laptop\n"));
+        assert!(!key.matches("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIK1 me@laptop"));
     }
 }
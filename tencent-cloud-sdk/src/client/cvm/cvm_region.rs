@@ -0,0 +1,86 @@
+use serde::Deserialize;
+
+use crate::{
+    client::action::{EmptyRequest, impl_action},
+    constant::Region,
+    error::Result,
+};
+
+use super::*;
+
+pub struct CVMRegionBuilder {
+    client: Arc<TencentCloudBaseClient>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DescribeRegionsResponse {
+    pub total_count: usize,
+    pub region_set: Vec<RegionInfo>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct RegionInfo {
+    /// [`Region::Other`] for regions newer than this crate
+    pub region: Region,
+    /// e.g. `华东地区(南京)`
+    pub region_name: String,
+    /// `AVAILABLE` / `UNAVAILABLE`
+    pub region_state: String,
+}
+
+impl RegionInfo {
+    pub fn is_available(&self) -> bool {
+        self.region_state == "AVAILABLE"
+    }
+}
+
+impl_action!(DescribeRegions: SERVICE, VERSION, EmptyRequest => DescribeRegionsResponse);
+
+impl CVMRegionBuilder {
+    pub fn new(client: Arc<TencentCloudBaseClient>) -> Self {
+        Self { client }
+    }
+
+    /// regions where cvm is offered to this account
+    pub async fn describe_regions(&self) -> Result<Vec<RegionInfo>> {
+        // the answer is the same for every region
+        let body = self
+            .client
+            .call::<DescribeRegions>(&Region::Guangzhou, &EmptyRequest {})
+            .await?;
+        Ok(body.region_set)
+    }
+
+    /// `None` if `region` is unknown or unavailable
+    pub async fn find_region(&self, region: &Region) -> Result<Option<RegionInfo>> {
+        Ok(self
+            .describe_regions()
+            .await?
+            .into_iter()
+            .find(|r| &r.region == region && r.is_available()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_region_info() {
+        let body: DescribeRegionsResponse = serde_json::from_value(json!({
+            "TotalCount": 2,
+            "RegionSet": [
+                {"Region": "ap-nanjing", "RegionName": "华东地区(南京)", "RegionState": "AVAILABLE"},
+                {"Region": "ap-new-city", "RegionName": "新地区", "RegionState": "AVAILABLE"},
+            ],
+        }))
+        .unwrap();
+        assert_eq!(body.region_set[0].region, Region::Nanjing);
+        assert_eq!(body.region_set[1].region, Region::Other("ap-new-city".to_owned()));
+        assert!(body.region_set[1].is_available());
+    }
+}
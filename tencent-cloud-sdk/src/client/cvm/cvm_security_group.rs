@@ -1,30 +1,65 @@
 use std::sync::Arc;
 
-use serde::Deserialize;
-use serde_json::json;
+use serde::{Deserialize, Serialize};
+use strum::Display;
+use tracing::debug;
 
 use crate::{
-    client::{TencentCloudBaseClient, ACTION_HEADER, REGION_HEADER},
+    client::{
+        TencentCloudBaseClient,
+        action::{EmptyResponse, Filter, Tag, impl_action},
+    },
     constant::Region,
+    error::Result,
 };
 
+use super::{VPC_SERVICE, VPC_VERSION};
+
 pub struct SecurityGroupBuilder {
     client: Arc<TencentCloudBaseClient>,
-    service_name: String,
-    version: String,
 }
 
-const DESCRIBE_SECURITY_GROUPS: &str = "DescribeSecurityGroups";
+/// max `Limit` of DescribeSecurityGroups
+const DESCRIBE_SECURITY_GROUPS_MAX_LIMIT: usize = 100;
 
-#[derive(Debug, Deserialize)]
+/// DescribeSecurityGroupsRequest, `Offset` / `Limit` are strings in the vpc api
+#[derive(Debug, Default, Clone, Serialize)]
 #[serde(rename_all = "PascalCase")]
-pub struct DescribeSecurityGroups {
-    pub response: DescribeSecurityGroupsInner,
+pub struct DescribeSecurityGroupsRequest {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub filters: Vec<Filter>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<String>,
+    /// 20 by default, at most 100
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<String>,
+}
+
+impl DescribeSecurityGroupsRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+    /// fuzzy match
+    pub fn security_group_name(self, name: &str) -> Self {
+        self.filter(Filter::new("security-group-name", [name]))
+    }
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = Some(offset.to_string());
+        self
+    }
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit.to_string());
+        self
+    }
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "PascalCase")]
-pub struct DescribeSecurityGroupsInner {
+pub struct DescribeSecurityGroupsResponse {
     pub total_count: usize,
     pub security_group_set: Vec<SecurityGroupInfo>,
 }
@@ -40,27 +75,335 @@ pub struct SecurityGroupInfo {
     pub created_time: String,
 }
 
-impl SecurityGroupBuilder {
-    pub fn new(client: Arc<TencentCloudBaseClient>) -> Self {
+/// CreateSecurityGroupRequest
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CreateSecurityGroupRequest {
+    pub group_name: String,
+    pub group_description: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<Tag>,
+}
+
+impl CreateSecurityGroupRequest {
+    pub fn new(group_name: impl Into<String>, group_description: impl Into<String>) -> Self {
+        Self {
+            group_name: group_name.into(),
+            group_description: group_description.into(),
+            tags: vec![],
+        }
+    }
+    pub fn tag(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.tags.push(Tag {
+            key: key.into(),
+            value: value.into(),
+        });
+        self
+    }
+}
+
+/// CreateSecurityGroupResponse
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CreateSecurityGroupResponse {
+    pub security_group: SecurityGroupInfo,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display)]
+#[serde(rename_all = "UPPERCASE")]
+#[strum(serialize_all = "UPPERCASE")]
+pub enum Protocol {
+    Tcp,
+    Udp,
+    Icmp,
+    Icmpv6,
+    All,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum PolicyAction {
+    ACCEPT,
+    DROP,
+}
+
+/// one rule, unset fields are left out of requests
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SecurityGroupPolicy {
+    /// position in its direction, from 0
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub policy_index: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<Protocol>,
+    /// `ALL`, `8211`, `8000-8100` or `80,443`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<String>,
+    /// e.g. `0.0.0.0/0`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cidr_block: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action: Option<PolicyAction>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub policy_description: Option<String>,
+}
+
+impl SecurityGroupPolicy {
+    /// accept `protocol` on `port` from `cidr_block`
+    pub fn accept(protocol: Protocol, port: impl ToString, cidr_block: impl Into<String>) -> Self {
         Self {
-            client,
-            service_name: "vpc".into(),
-            version: "2017-03-12".into(),
+            protocol: Some(protocol),
+            port: Some(port.to_string()),
+            cidr_block: Some(cidr_block.into()),
+            action: Some(PolicyAction::ACCEPT),
+            ..Default::default()
         }
     }
+    /// insert at `index` when created, 0 for the top
+    pub fn index(mut self, index: i64) -> Self {
+        self.policy_index = Some(index);
+        self
+    }
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.policy_description = Some(description.into());
+        self
+    }
+    /// same protocol, port, cidr and action, whatever the index and description
+    pub fn same_rule(&self, other: &Self) -> bool {
+        self.protocol == other.protocol
+            && self.port == other.port
+            && self.cidr_block == other.cidr_block
+            && self.action == other.action
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SecurityGroupPolicySet {
+    /// bumped on every change
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ingress: Vec<SecurityGroupPolicy>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub egress: Vec<SecurityGroupPolicy>,
+}
+
+/// DescribeSecurityGroupPoliciesRequest
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DescribeSecurityGroupPoliciesRequest {
+    pub security_group_id: String,
+}
+
+/// DescribeSecurityGroupPoliciesResponse
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DescribeSecurityGroupPoliciesResponse {
+    pub security_group_policy_set: SecurityGroupPolicySet,
+}
+
+/// CreateSecurityGroupPoliciesRequest / DeleteSecurityGroupPoliciesRequest
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SecurityGroupPoliciesRequest {
+    pub security_group_id: String,
+    pub security_group_policy_set: SecurityGroupPolicySet,
+}
+
+impl_action!(DescribeSecurityGroups: VPC_SERVICE, VPC_VERSION, DescribeSecurityGroupsRequest => DescribeSecurityGroupsResponse);
+impl_action!(CreateSecurityGroup: VPC_SERVICE, VPC_VERSION, CreateSecurityGroupRequest => CreateSecurityGroupResponse, IDEMPOTENT = false);
+impl_action!(DescribeSecurityGroupPolicies: VPC_SERVICE, VPC_VERSION, DescribeSecurityGroupPoliciesRequest => DescribeSecurityGroupPoliciesResponse);
+impl_action!(CreateSecurityGroupPolicies: VPC_SERVICE, VPC_VERSION, SecurityGroupPoliciesRequest => EmptyResponse, IDEMPOTENT = false);
+impl_action!(DeleteSecurityGroupPolicies: VPC_SERVICE, VPC_VERSION, SecurityGroupPoliciesRequest => EmptyResponse);
+
+impl SecurityGroupBuilder {
+    pub fn new(client: Arc<TencentCloudBaseClient>) -> Self {
+        Self { client }
+    }
+    /// one page of DescribeSecurityGroups
     pub async fn describe_security_groups(
         &self,
         region: &Region,
-    ) -> anyhow::Result<Vec<SecurityGroupInfo>> {
-        let resp = self
+        request: &DescribeSecurityGroupsRequest,
+    ) -> Result<DescribeSecurityGroupsResponse> {
+        self.client.call::<DescribeSecurityGroups>(region, request).await
+    }
+
+    /// the group named exactly `name`, case insensitive, searched through every page
+    pub async fn find_security_group(&self, region: &Region, name: &str) -> Result<Option<SecurityGroupInfo>> {
+        let mut offset = 0;
+        loop {
+            let request = DescribeSecurityGroupsRequest::new()
+                .security_group_name(name)
+                .offset(offset)
+                .limit(DESCRIBE_SECURITY_GROUPS_MAX_LIMIT);
+            let page = self.describe_security_groups(region, &request).await?;
+            offset += page.security_group_set.len();
+            let done = page.security_group_set.is_empty() || offset >= page.total_count;
+            if let Some(sg) = page
+                .security_group_set
+                .into_iter()
+                .find(|sg| sg.security_group_name.eq_ignore_ascii_case(name))
+            {
+                return Ok(Some(sg));
+            }
+            if done {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// a new group without rules
+    pub async fn create_security_group(
+        &self,
+        region: &Region,
+        request: &CreateSecurityGroupRequest,
+    ) -> Result<SecurityGroupInfo> {
+        let body = self.client.call::<CreateSecurityGroup>(region, request).await?;
+        debug!("body: {body:?}");
+        Ok(body.security_group)
+    }
+
+    pub async fn describe_security_group_policies(
+        &self,
+        region: &Region,
+        security_group_id: &str,
+    ) -> Result<SecurityGroupPolicySet> {
+        let request = DescribeSecurityGroupPoliciesRequest {
+            security_group_id: security_group_id.to_owned(),
+        };
+        let body = self
             .client
-            .post(&self.service_name, &self.version)
-            .header(ACTION_HEADER, DESCRIBE_SECURITY_GROUPS)
-            .header(REGION_HEADER, region.to_string())
-            .json(&json!({}))
-            .send()
+            .call::<DescribeSecurityGroupPolicies>(region, &request)
             .await?;
-        let body: DescribeSecurityGroups = resp.json().await?;
-        Ok(body.response.security_group_set)
+        Ok(body.security_group_policy_set)
+    }
+
+    /// append `policies`, only one of ingress and egress can be set in a call
+    pub async fn create_security_group_policies(
+        &self,
+        region: &Region,
+        security_group_id: &str,
+        policies: SecurityGroupPolicySet,
+    ) -> Result<()> {
+        let request = SecurityGroupPoliciesRequest {
+            security_group_id: security_group_id.to_owned(),
+            security_group_policy_set: policies,
+        };
+        let body = self
+            .client
+            .call::<CreateSecurityGroupPolicies>(region, &request)
+            .await?;
+        debug!("body: {body:?}");
+        Ok(())
+    }
+
+    /// by `policy_index` only, or by the whole rule
+    pub async fn delete_security_group_policies(
+        &self,
+        region: &Region,
+        security_group_id: &str,
+        policies: SecurityGroupPolicySet,
+    ) -> Result<()> {
+        let request = SecurityGroupPoliciesRequest {
+            security_group_id: security_group_id.to_owned(),
+            security_group_policy_set: policies,
+        };
+        let body = self
+            .client
+            .call::<DeleteSecurityGroupPolicies>(region, &request)
+            .await?;
+        debug!("body: {body:?}");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        client::{TencentCloudClient, mock::MockServer},
+        config::{ClientConfig, Endpoint},
+    };
+
+    #[test]
+    fn test_security_group_policies() {
+        let set: SecurityGroupPolicySet = serde_json::from_value(json!({
+            "Version": "3",
+            "Egress": [],
+            "Ingress": [{
+                "PolicyIndex": 0,
+                "Protocol": "UDP",
+                "Port": "8211",
+                "ServiceTemplate": {"ServiceId": "", "ServiceGroupId": ""},
+                "CidrBlock": "0.0.0.0/0",
+                "Ipv6CidrBlock": "",
+                "SecurityGroupId": "",
+                "AddressTemplate": {"AddressId": "", "AddressGroupId": ""},
+                "Action": "ACCEPT",
+                "PolicyDescription": "palworld",
+                "ModifyTime": "2024-01-25 12:00:00",
+            }],
+        }))
+        .unwrap();
+        let rule = SecurityGroupPolicy::accept(Protocol::Udp, 8211, "0.0.0.0/0");
+        assert!(set.ingress[0].same_rule(&rule));
+        assert!(!set.ingress[0].same_rule(&SecurityGroupPolicy::accept(Protocol::Tcp, 8211, "0.0.0.0/0")));
+        assert!(!set.ingress[0].same_rule(&SecurityGroupPolicy::accept(Protocol::Udp, 8212, "0.0.0.0/0")));
+        assert!(!set.ingress[0].same_rule(&SecurityGroupPolicy::accept(Protocol::Udp, 8211, "10.0.0.0/8")));
+
+        let request = SecurityGroupPoliciesRequest {
+            security_group_id: "sg-1".to_owned(),
+            security_group_policy_set: SecurityGroupPolicySet {
+                ingress: vec![rule.description("game")],
+                ..Default::default()
+            },
+        };
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({
+                "SecurityGroupId": "sg-1",
+                "SecurityGroupPolicySet": {"Ingress": [{
+                    "Protocol": "UDP",
+                    "Port": "8211",
+                    "CidrBlock": "0.0.0.0/0",
+                    "Action": "ACCEPT",
+                    "PolicyDescription": "game",
+                }]},
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_find_security_group() {
+        let group = |id: &str, name: &str| {
+            json!({
+                "SecurityGroupId": id, "SecurityGroupName": name, "SecurityGroupDesc": "", "ProjectId": "0",
+                "IsDefault": false, "CreatedTime": "2024-01-25 12:00:00",
+            })
+        };
+        let server = MockServer::start(vec![
+            json!({"Response": {"TotalCount": 2, "SecurityGroupSet": [group("sg-1", "palworld-old")], "RequestId": "req-1"}}),
+            json!({"Response": {"TotalCount": 2, "SecurityGroupSet": [group("sg-2", "PalWorld")], "RequestId": "req-2"}}),
+        ])
+        .await;
+        let config = ClientConfig::new("ak", "sk").endpoint(Endpoint::Custom(server.url.clone()));
+        let client = TencentCloudClient::new(&config).unwrap();
+
+        let sg = client
+            .cvm()
+            .security_group()
+            .find_security_group(&Region::Nanjing, "palworld")
+            .await
+            .unwrap();
+        assert_eq!(sg.map(|sg| sg.security_group_id).as_deref(), Some("sg-2"));
+        let requests = server.requests();
+        assert_eq!(
+            requests[1].json(),
+            json!({"Filters": [{"Name": "security-group-name", "Values": ["palworld"]}], "Offset": "1", "Limit": "100"})
+        );
     }
 }
@@ -1,67 +1,67 @@
-use reqwest::StatusCode;
 use serde::Deserialize;
-use serde_json::json;
 
 use crate::{
-    client::constant::{ACTION_HEADER, REGION_HEADER},
+    client::action::{EmptyRequest, impl_action},
     constant::Region,
+    error::Result,
 };
 
-const DESCRIBE_ZONES: &str = "DescribeZones";
-
 use super::*;
 pub struct CVMZoneBuilder {
     client: Arc<TencentCloudBaseClient>,
-    service_name: String,
-    version: String,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "PascalCase")]
-struct DescribeZoneResponse {
-    pub response: DescribeZoneResponseInner,
-}
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "PascalCase")]
-struct DescribeZoneResponseInner {
+pub struct DescribeZonesResponse {
     #[allow(unused)]
     pub total_count: usize,
     pub zone_set: Vec<ZoneInfo>,
 }
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "PascalCase")]
-struct ZoneInfo {
+pub struct ZoneInfo {
+    /// e.g. `ap-nanjing-1`
     pub zone: String,
+    /// e.g. `南京一区`
+    pub zone_name: String,
+    /// e.g. `330001`
+    pub zone_id: String,
+    pub zone_state: ZoneState,
 }
+
+impl ZoneInfo {
+    pub fn is_available(&self) -> bool {
+        self.zone_state == ZoneState::AVAILABLE
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub enum ZoneState {
+    AVAILABLE,
+    UNAVAILABLE,
+}
+
+impl_action!(DescribeZones: SERVICE, VERSION, EmptyRequest => DescribeZonesResponse);
+
 impl CVMZoneBuilder {
     pub fn new(client: Arc<TencentCloudBaseClient>) -> Self {
-        Self {
-            client,
-            service_name: "cvm".into(),
-            version: "2017-03-12".into(),
-        }
+        Self { client }
+    }
+    /// every zone of `region`, available or not
+    pub async fn describe_zones(&self, region: &Region) -> Result<Vec<ZoneInfo>> {
+        let body = self.client.call::<DescribeZones>(region, &EmptyRequest {}).await?;
+        Ok(body.zone_set)
     }
-    pub async fn describe_zone(&self, region: &Region) -> anyhow::Result<Option<Vec<String>>> {
-        let resp = self
-            .client
-            .post(&self.service_name, &self.version)
-            .header(ACTION_HEADER, DESCRIBE_ZONES)
-            .header(REGION_HEADER, region.to_string())
-            .json(&json!({}))
-            .send()
-            .await?;
-        match resp.status() {
-            StatusCode::OK => {
-                let body: DescribeZoneResponse = resp.json().await?;
-                // println!("DescribeZoneResponse body: {body:?}");
-                Ok(Some(
-                    body.response.zone_set.into_iter().map(|z| z.zone).collect(),
-                ))
-            }
-            rest => Err(anyhow::anyhow!(
-                "err get code {rest}, msg {}",
-                resp.text().await?
-            )),
-        }
+    /// names of the available zones
+    pub async fn describe_zone(&self, region: &Region) -> Result<Option<Vec<String>>> {
+        let zones = self.describe_zones(region).await?;
+        Ok(Some(
+            zones
+                .into_iter()
+                .filter(ZoneInfo::is_available)
+                .map(|z| z.zone)
+                .collect(),
+        ))
     }
 }
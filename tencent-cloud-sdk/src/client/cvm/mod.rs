@@ -2,11 +2,21 @@ use std::sync::Arc;
 
 use super::TencentCloudBaseClient;
 
+pub mod cvm_address;
+pub mod cvm_image;
 pub mod cvm_instance;
+pub mod cvm_instance_type;
 pub mod cvm_key;
+pub mod cvm_region;
 pub mod cvm_security_group;
 pub mod cvm_zone;
 
+const SERVICE: &str = "cvm";
+const VERSION: &str = "2017-03-12";
+/// security groups and eips belong to vpc
+const VPC_SERVICE: &str = "vpc";
+const VPC_VERSION: &str = "2017-03-12";
+
 pub struct CVMBuilder {
     client: Arc<TencentCloudBaseClient>,
 }
@@ -20,6 +30,18 @@ impl CVMBuilder {
         cvm_instance::CVMInstanceBuilder::new(self.client.clone())
     }
 
+    pub fn images(&self) -> cvm_image::CVMImageBuilder {
+        cvm_image::CVMImageBuilder::new(self.client.clone())
+    }
+
+    pub fn instance_types(&self) -> cvm_instance_type::CVMInstanceTypeBuilder {
+        cvm_instance_type::CVMInstanceTypeBuilder::new(self.client.clone())
+    }
+
+    pub fn regions(&self) -> cvm_region::CVMRegionBuilder {
+        cvm_region::CVMRegionBuilder::new(self.client.clone())
+    }
+
     pub fn zone(&self) -> cvm_zone::CVMZoneBuilder {
         cvm_zone::CVMZoneBuilder::new(self.client.clone())
     }
@@ -28,6 +50,10 @@ impl CVMBuilder {
         cvm_key::CVMKeyBuilder::new(self.client.clone())
     }
 
+    pub fn addresses(&self) -> cvm_address::AddressBuilder {
+        cvm_address::AddressBuilder::new(self.client.clone())
+    }
+
     pub fn security_group(&self) -> cvm_security_group::SecurityGroupBuilder {
         cvm_security_group::SecurityGroupBuilder::new(self.client.clone())
     }
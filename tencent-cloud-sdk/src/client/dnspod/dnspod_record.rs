@@ -0,0 +1,215 @@
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::{client::action::impl_action, constant::Region, error::Result};
+
+use super::*;
+
+pub struct DnspodRecordBuilder {
+    client: Arc<TencentCloudBaseClient>,
+}
+
+/// dnspod isn't regional, every request is sent with this one
+const REGION: Region = Region::Guangzhou;
+
+/// the line answered for every resolver
+pub const DEFAULT_RECORD_LINE: &str = "默认";
+
+/// returned by DescribeRecordList instead of an empty list
+const NO_DATA_OF_RECORD: &str = "ResourceNotFound.NoDataOfRecord";
+
+/// DescribeRecordListRequest
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DescribeRecordListRequest {
+    /// e.g. `example.com`
+    pub domain: String,
+    /// e.g. `pal`, `@` for the domain itself
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subdomain: Option<String>,
+    /// e.g. `A`, `CNAME`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub record_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u32>,
+    /// 100 by default, at most 3000
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+}
+
+/// DescribeRecordListResponse
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DescribeRecordListResponse {
+    pub record_list: Vec<RecordListItem>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct RecordListItem {
+    pub record_id: u64,
+    /// subdomain
+    pub name: String,
+    #[serde(rename = "Type")]
+    pub record_type: String,
+    pub line: String,
+    pub value: String,
+    #[serde(rename = "TTL")]
+    pub ttl: u32,
+    /// `ENABLE` / `DISABLE`
+    pub status: String,
+    #[serde(default)]
+    pub updated_on: String,
+}
+
+/// CreateRecordRequest / ModifyRecordRequest, `record_id` only for ModifyRecord
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct RecordRequest {
+    pub domain: String,
+    pub sub_domain: String,
+    pub record_type: String,
+    pub record_line: String,
+    pub value: String,
+    #[serde(rename = "TTL", skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub record_id: Option<u64>,
+}
+
+impl RecordRequest {
+    /// on the default line
+    pub fn new(
+        domain: impl Into<String>,
+        sub_domain: impl Into<String>,
+        record_type: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Self {
+        Self {
+            domain: domain.into(),
+            sub_domain: sub_domain.into(),
+            record_type: record_type.into(),
+            record_line: DEFAULT_RECORD_LINE.to_owned(),
+            value: value.into(),
+            ttl: None,
+            record_id: None,
+        }
+    }
+    /// seconds, the lower bound depends on the dnspod plan
+    pub fn ttl(mut self, ttl: u32) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+    pub fn record_id(mut self, record_id: u64) -> Self {
+        self.record_id = Some(record_id);
+        self
+    }
+}
+
+/// CreateRecordResponse / ModifyRecordResponse
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct RecordResponse {
+    pub record_id: u64,
+}
+
+impl_action!(DescribeRecordList: SERVICE, VERSION, DescribeRecordListRequest => DescribeRecordListResponse);
+impl_action!(CreateRecord: SERVICE, VERSION, RecordRequest => RecordResponse, IDEMPOTENT = false);
+impl_action!(ModifyRecord: SERVICE, VERSION, RecordRequest => RecordResponse);
+
+impl DnspodRecordBuilder {
+    pub fn new(client: Arc<TencentCloudBaseClient>) -> Self {
+        Self { client }
+    }
+    /// empty if nothing matches
+    pub async fn describe_record_list(&self, request: &DescribeRecordListRequest) -> Result<Vec<RecordListItem>> {
+        match self.client.call::<DescribeRecordList>(&REGION, request).await {
+            Ok(body) => Ok(body.record_list),
+            Err(e) if e.code() == Some(NO_DATA_OF_RECORD) => Ok(vec![]),
+            Err(e) => Err(e),
+        }
+    }
+    /// return the record id
+    pub async fn create_record(&self, request: &RecordRequest) -> Result<u64> {
+        let body = self.client.call::<CreateRecord>(&REGION, request).await?;
+        Ok(body.record_id)
+    }
+    /// `request.record_id` is required
+    pub async fn modify_record(&self, request: &RecordRequest) -> Result<u64> {
+        let body = self.client.call::<ModifyRecord>(&REGION, request).await?;
+        Ok(body.record_id)
+    }
+    /// point the record of `sub_domain` on the default line to `request.value`, created if missing
+    pub async fn upsert_record(&self, request: &RecordRequest) -> Result<u64> {
+        let list = DescribeRecordListRequest {
+            domain: request.domain.clone(),
+            subdomain: Some(request.sub_domain.clone()),
+            record_type: Some(request.record_type.clone()),
+            ..Default::default()
+        };
+        let existing = self
+            .describe_record_list(&list)
+            .await?
+            .into_iter()
+            .find(|r| r.line == request.record_line);
+        match existing {
+            Some(record) if record.value == request.value && request.ttl.is_none_or(|ttl| ttl == record.ttl) => {
+                debug!("record {} is up to date", record.record_id);
+                Ok(record.record_id)
+            }
+            Some(record) => self.modify_record(&request.clone().record_id(record.record_id)).await,
+            None => self.create_record(request).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        client::{ACTION_HEADER, TencentCloudClient, mock::MockServer},
+        config::{ClientConfig, Endpoint},
+    };
+
+    #[tokio::test]
+    async fn test_upsert_record() {
+        let server = MockServer::start(vec![
+            json!({"Response": {"Error": {"Code": "ResourceNotFound.NoDataOfRecord", "Message": "no record"}, "RequestId": "req-1"}}),
+            json!({"Response": {"RecordId": 100, "RequestId": "req-2"}}),
+            json!({"Response": {"RecordCountInfo": {"SubdomainCount": 1, "ListCount": 1, "TotalCount": 1}, "RecordList": [{
+                "RecordId": 100, "Name": "pal", "Type": "A", "Line": "默认", "LineId": "0", "Value": "203.0.113.7",
+                "TTL": 60, "Status": "ENABLE", "UpdatedOn": "2024-01-25 12:00:00", "MX": 0, "Weight": null,
+            }], "RequestId": "req-3"}}),
+            json!({"Response": {"RecordId": 100, "RequestId": "req-4"}}),
+        ])
+        .await;
+        let config = ClientConfig::new("ak", "sk").endpoint(Endpoint::Custom(server.url.clone()));
+        let client = TencentCloudClient::new(&config).unwrap();
+        let records = client.dnspod().records();
+
+        let request = RecordRequest::new("example.com", "pal", "A", "203.0.113.7").ttl(60);
+        assert_eq!(records.upsert_record(&request).await.unwrap(), 100);
+        let request = RecordRequest::new("example.com", "pal", "A", "127.0.0.1").ttl(60);
+        assert_eq!(records.upsert_record(&request).await.unwrap(), 100);
+
+        let requests = server.requests();
+        let actions: Vec<_> = requests.iter().map(|r| r.header(ACTION_HEADER).unwrap()).collect();
+        assert_eq!(
+            actions,
+            [
+                "DescribeRecordList",
+                "CreateRecord",
+                "DescribeRecordList",
+                "ModifyRecord"
+            ]
+        );
+        assert_eq!(
+            requests[1].json(),
+            json!({"Domain": "example.com", "SubDomain": "pal", "RecordType": "A", "RecordLine": "默认", "Value": "203.0.113.7", "TTL": 60})
+        );
+        assert_eq!(requests[3].json()["RecordId"], 100);
+        assert_eq!(requests[3].json()["Value"], "127.0.0.1");
+    }
+}
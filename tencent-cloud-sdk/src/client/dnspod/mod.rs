@@ -0,0 +1,22 @@
+use std::sync::Arc;
+
+use super::TencentCloudBaseClient;
+
+pub mod dnspod_record;
+
+const SERVICE: &str = "dnspod";
+const VERSION: &str = "2021-03-23";
+
+pub struct DnspodBuilder {
+    client: Arc<TencentCloudBaseClient>,
+}
+
+impl DnspodBuilder {
+    pub fn new(client: Arc<TencentCloudBaseClient>) -> Self {
+        Self { client }
+    }
+
+    pub fn records(&self) -> dnspod_record::DnspodRecordBuilder {
+        dnspod_record::DnspodRecordBuilder::new(self.client.clone())
+    }
+}
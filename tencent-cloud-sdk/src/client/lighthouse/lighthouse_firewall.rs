@@ -1,33 +1,28 @@
-use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
-use serde_json::json;
 
 use crate::{
-    client::{ACTION_HEADER, REGION_HEADER},
+    client::action::{EmptyResponse, impl_action},
     constant::Region,
+    error::Result,
 };
 
 use super::*;
 
 pub struct LighthouseFirewallBuilder {
     client: Arc<TencentCloudBaseClient>,
-    service_name: String,
-    version: String,
 }
 
-const DESCRIBE_FIREWALL_RULES_ACTION: &str = "DescribeFirewallRules";
-const MODIFY_FIREWALL_RULES_ACTION: &str = "ModifyFirewallRules";
-
-/// DescribeFirewallRulesResponse
-#[derive(Debug, Deserialize, Default)]
+/// DescribeFirewallRulesRequest
+#[derive(Debug, Serialize)]
 #[serde(rename_all = "PascalCase")]
-pub struct DescribeFirewallRulesResponse {
-    pub response: DescribeFirewallRulesResponseInner,
+pub struct DescribeFirewallRulesRequest {
+    pub instance_id: String,
 }
 
+/// DescribeFirewallRulesResponse
 #[derive(Debug, Deserialize, Default)]
 #[serde(rename_all = "PascalCase")]
-pub struct DescribeFirewallRulesResponseInner {
+pub struct DescribeFirewallRulesResponse {
     pub firewall_rule_set: Vec<FirewallRule>,
 }
 
@@ -41,39 +36,35 @@ pub struct FirewallRule {
     pub firewall_rule_description: String,
 }
 
+/// ModifyFirewallRulesRequest
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ModifyFirewallRulesRequest {
+    pub instance_id: String,
+    pub firewall_rules: Vec<FirewallRule>,
+}
+
+impl_action!(DescribeFirewallRules: SERVICE, VERSION, DescribeFirewallRulesRequest => DescribeFirewallRulesResponse);
+impl_action!(ModifyFirewallRules: SERVICE, VERSION, ModifyFirewallRulesRequest => EmptyResponse);
+
 impl LighthouseFirewallBuilder {
     pub fn new(client: Arc<TencentCloudBaseClient>) -> Self {
-        Self {
-            client,
-            service_name: "lighthouse".into(),
-            version: "2020-03-24".into(),
-        }
+        Self { client }
     }
 
     pub async fn describe_firewall_rules(
         &self,
         region: &Region,
         instance_id: &str,
-    ) -> anyhow::Result<DescribeFirewallRulesResponse> {
-        let resp = self
-            .client
-            .post(&self.service_name, &self.version)
-            .header(ACTION_HEADER, DESCRIBE_FIREWALL_RULES_ACTION)
-            .header(REGION_HEADER, region.to_string())
-            .json(&json!(
-                {
-                    "InstanceId": instance_id,
-                }
-            ))
-            .send()
-            .await?;
-        match resp.status() {
-            StatusCode::OK => Ok(resp.json().await?),
-            rest => Err(anyhow::anyhow!(
-                "describe_firewall_rules failed with status: {}",
-                rest
-            )),
-        }
+    ) -> Result<DescribeFirewallRulesResponse> {
+        self.client
+            .call::<DescribeFirewallRules>(
+                region,
+                &DescribeFirewallRulesRequest {
+                    instance_id: instance_id.to_owned(),
+                },
+            )
+            .await
     }
 
     pub async fn modify_firewall_rules(
@@ -81,26 +72,16 @@ impl LighthouseFirewallBuilder {
         region: &Region,
         instance_id: &str,
         rules: Vec<FirewallRule>,
-    ) -> anyhow::Result<()> {
-        let resp = self
-            .client
-            .post(&self.service_name, &self.version)
-            .header(ACTION_HEADER, MODIFY_FIREWALL_RULES_ACTION)
-            .header(REGION_HEADER, region.to_string())
-            .json(&json!(
-                {
-                    "InstanceId": instance_id,
-                    "FirewallRules": rules,
-                }
-            ))
-            .send()
+    ) -> Result<()> {
+        self.client
+            .call::<ModifyFirewallRules>(
+                region,
+                &ModifyFirewallRulesRequest {
+                    instance_id: instance_id.to_owned(),
+                    firewall_rules: rules,
+                },
+            )
             .await?;
-        match resp.status() {
-            StatusCode::OK => Ok(()),
-            rest => Err(anyhow::anyhow!(
-                "modify_firewall_rules failed with status: {}",
-                rest
-            )),
-        }
+        Ok(())
     }
 }
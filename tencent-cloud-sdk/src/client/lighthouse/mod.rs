@@ -4,6 +4,9 @@ use super::TencentCloudBaseClient;
 
 pub mod lighthouse_firewall;
 
+const SERVICE: &str = "lighthouse";
+const VERSION: &str = "2020-03-24";
+
 pub struct LighthouseBuilder {
     client: Arc<TencentCloudBaseClient>,
 }
@@ -2,15 +2,35 @@ use std::sync::Arc;
 
 use reqwest::header::{self};
 use reqwest_middleware::{ClientBuilder, ClientWithMiddleware, RequestBuilder};
+use tracing::debug;
 
+mod rate_limit;
+mod retry;
 mod signature;
+pub use rate_limit::RateLimitPolicy;
+use rate_limit::{RateLimitMiddleware, RateLimiter};
+pub use retry::RetryPolicy;
+use retry::{NotIdempotent, RetryMiddleware};
 use signature::{SignatureContext, SignatureMiddleware};
 
-use crate::config::ClientConfig;
+use crate::{
+    config::{ClientConfig, Endpoint},
+    constant::Region,
+    credential::{Credential, CredentialProvider},
+    error::{Result, parse_body},
+};
 
+pub mod action;
+pub mod cbs;
 mod constant;
 pub mod cvm;
+pub mod dnspod;
 pub mod lighthouse;
+#[cfg(test)]
+mod mock;
+pub mod sts;
+
+use action::{Action, EmptyResponse, ResponseEnvelope};
 
 pub use constant::*;
 
@@ -20,72 +40,280 @@ pub struct TencentCloudClient {
 }
 
 impl TencentCloudClient {
-    pub fn new(config: &ClientConfig) -> Self {
-        Self {
-            client: Arc::new(TencentCloudBaseClient::new(
-                config.ak.clone(),
-                config.sk.clone(),
-            )),
-        }
+    /// the retry policy and rate limiter of `config` are shared by every builder and clone of this client
+    pub fn new(config: &ClientConfig) -> Result<Self> {
+        Ok(Self {
+            client: Arc::new(TencentCloudBaseClient::new(config)?),
+        })
+    }
+    pub fn cbs(&self) -> cbs::CBSBuilder {
+        cbs::CBSBuilder::new(self.client.clone())
     }
     pub fn cvm(&self) -> cvm::CVMBuilder {
         cvm::CVMBuilder::new(self.client.clone())
     }
+    pub fn dnspod(&self) -> dnspod::DnspodBuilder {
+        dnspod::DnspodBuilder::new(self.client.clone())
+    }
     pub fn lighthouse(&self) -> lighthouse::LighthouseBuilder {
         lighthouse::LighthouseBuilder::new(self.client.clone())
     }
+    pub fn sts(&self) -> sts::StsBuilder {
+        sts::StsBuilder::new(self.client.clone())
+    }
 }
 
 #[derive(Debug)]
 pub struct TencentCloudBaseClient {
     client: ClientWithMiddleware,
-    ak: String,
-    sk: String,
-    base_url: String,
+    credential: Arc<dyn CredentialProvider>,
+    endpoint: Endpoint,
 }
 
 impl TencentCloudBaseClient {
-    pub fn new(ak: String, sk: String) -> Self {
-        let reqwest_client = reqwest::Client::new();
-        let client = ClientBuilder::new(reqwest_client)
+    pub fn new(config: &ClientConfig) -> Result<Self> {
+        let mut builder = reqwest::Client::builder();
+        if let Some(timeout) = config.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = config.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(proxy) = &config.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+        }
+        if let Some(user_agent) = &config.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+        if let Some(language) = config.language {
+            let mut headers = header::HeaderMap::new();
+            headers.insert(LANGUAGE_HEADER, header::HeaderValue::from_static(language.into()));
+            builder = builder.default_headers(headers);
+        }
+
+        // retry first, so every attempt waits for a token and is signed again
+        let client = ClientBuilder::new(builder.build()?)
+            .with(RetryMiddleware {
+                policy: config.retry_policy.clone(),
+            })
+            .with(RateLimitMiddleware {
+                limiter: RateLimiter::new(config.rate_limit_policy.clone()),
+            })
             .with(SignatureMiddleware)
             .build();
-        Self {
+        Ok(Self {
             client,
-            ak,
-            sk,
-            base_url: "https://tencentcloudapi.com".to_owned(),
-        }
-        // base_url: "https://cvm.tencentcloudapi.com".to_owned(),
+            credential: config.build_credential_provider(),
+            endpoint: config.endpoint.clone(),
+        })
     }
 
-    pub fn get(&self, service: &str, version: &str) -> RequestBuilder {
-        self.client
-            .get(build_service_api_url(&self.base_url, service))
-            .with_extension(self.signature_context(service, version))
+    pub async fn get(&self, service: &str, version: &str, region: &Region) -> Result<RequestBuilder> {
+        Ok(self
+            .client
+            .get(self.endpoint.url(service, &region.to_string()))
+            .with_extension(self.signature_context(service, version).await?))
     }
 
-    pub fn post(&self, service: &str, version: &str) -> RequestBuilder {
-        self.client
-            .post(build_service_api_url(&self.base_url, service))
-            .with_extension(self.signature_context(service, version))
+    pub async fn post(&self, service: &str, version: &str, region: &Region) -> Result<RequestBuilder> {
+        Ok(self
+            .client
+            .post(self.endpoint.url(service, &region.to_string()))
+            .with_extension(self.signature_context(service, version).await?))
     }
 
-    pub fn signature_context(&self, service: &str, version: &str) -> SignatureContext {
-        SignatureContext {
-            ak: self.ak.clone(),
-            sk: self.sk.clone(),
+    /// send `A` to `region`, decode `Response` or return `Response.Error`
+    pub async fn call<A: Action>(&self, region: &Region, request: &A::Request) -> Result<A::Response> {
+        let mut builder = self.post(A::SERVICE, A::VERSION, region).await?;
+        if !A::IDEMPOTENT {
+            builder = builder.with_extension(NotIdempotent);
+        }
+        let resp = builder
+            .header(ACTION_HEADER, A::ACTION)
+            .header(REGION_HEADER, region.to_string())
+            .json(request)
+            .send()
+            .await?;
+        let status = resp.status();
+        let body = resp.text().await?;
+        if A::SENSITIVE {
+            let request_id = serde_json::from_str::<ResponseEnvelope<EmptyResponse>>(&body)
+                .map(|body| body.response.request_id)
+                .unwrap_or_default();
+            debug!(
+                "{}:{} {region} response: {request_id} (redacted)",
+                A::SERVICE,
+                A::ACTION
+            );
+        } else {
+            debug!("{}:{} {region} response: {body}", A::SERVICE, A::ACTION);
+        }
+        let body: ResponseEnvelope<A::Response> = parse_body(status, &body)?;
+        Ok(body.response)
+    }
+
+    pub async fn credential(&self) -> Result<Credential> {
+        self.credential.credential().await
+    }
+
+    pub async fn signature_context(&self, service: &str, version: &str) -> Result<SignatureContext> {
+        let credential = self.credential().await?;
+        Ok(SignatureContext {
+            ak: credential.secret_id,
+            sk: credential.secret_key,
+            token: credential.token,
             signed_headers: Some(vec![header::CONTENT_TYPE, header::HOST]),
             service_name: service.to_owned(),
             version: version.to_owned(),
-        }
+        })
     }
 }
 
-fn build_service_api_url(url: &str, service_name: &str) -> String {
-    format!(
-        "https://{}.{}",
-        service_name,
-        &url[url.find("://").map_or(0, |x| x + 3)..]
-    )
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use super::{mock::MockServer, *};
+    use crate::{
+        client::{
+            action::{EmptyRequest, EmptyResponse, impl_action},
+            cvm::cvm_instance::RunInstancesRequest,
+        },
+        config::Language,
+        constant::InstanceType,
+        credential::StaticCredentialProvider,
+        error::ErrorKind,
+    };
+
+    #[tokio::test]
+    async fn test_call_custom_endpoint() {
+        let server = MockServer::start(vec![
+            json!({"Response": {"Error": {"Code": "RequestLimitExceeded", "Message": "slow down"}, "RequestId": "req-1"}}),
+            json!({"Response": {"TotalCount": 2, "ZoneSet": [
+                {"Zone": "ap-nanjing-1", "ZoneName": "南京一区", "ZoneId": "330001", "ZoneState": "AVAILABLE"},
+                {"Zone": "ap-nanjing-2", "ZoneName": "南京二区", "ZoneId": "330002", "ZoneState": "UNAVAILABLE"},
+            ], "RequestId": "req-2"}}),
+        ])
+        .await;
+        let config = ClientConfig::new("ak", "sk")
+            .endpoint(Endpoint::Custom(server.url.clone()))
+            .language(Language::EnUS)
+            .timeout(Duration::from_secs(5))
+            .retry_policy(RetryPolicy {
+                base_delay: Duration::from_millis(1),
+                ..Default::default()
+            });
+        let client = TencentCloudClient::new(&config).unwrap();
+
+        let zones = client.cvm().zone().describe_zone(&Region::Nanjing).await.unwrap();
+        assert_eq!(zones, Some(vec!["ap-nanjing-1".to_owned()]));
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        for request in &requests {
+            assert_eq!(request.header(ACTION_HEADER), Some("DescribeZones"));
+            assert_eq!(request.header(REGION_HEADER), Some("ap-nanjing"));
+            assert_eq!(request.header(LANGUAGE_HEADER), Some("en-US"));
+            assert!(request.header("authorization").is_some());
+            assert_eq!(request.json(), json!({}));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_call_with_session_token() {
+        let server = MockServer::start(vec![
+            json!({"Response": {"TotalCount": 0, "ZoneSet": [], "RequestId": "req-1"}}),
+        ])
+        .await;
+        let credential = Credential::new("id", "key").with_token("session-token", None);
+        let config = ClientConfig::with_provider(Arc::new(StaticCredentialProvider(credential)))
+            .endpoint(Endpoint::Custom(server.url.clone()));
+        let client = TencentCloudClient::new(&config).unwrap();
+
+        client.cvm().zone().describe_zone(&Region::Nanjing).await.unwrap();
+        let request = &server.requests()[0];
+        assert_eq!(request.header(TOKEN_HEADER), Some("session-token"));
+        assert!(request.header("authorization").unwrap().contains("Credential=id/"));
+    }
+
+    #[tokio::test]
+    async fn test_call_not_idempotent() {
+        impl_action!(CreateThing: "cvm", "2017-03-12", EmptyRequest => EmptyResponse, IDEMPOTENT = false);
+
+        let server = MockServer::start(vec![
+            json!({"Response": {"Error": {"Code": "RequestLimitExceeded", "Message": "slow down"}, "RequestId": "req-1"}}),
+            json!({"Response": {"Error": {"Code": "InternalError", "Message": "oops"}, "RequestId": "req-2"}}),
+            json!({"Response": {"RequestId": "req-3"}}),
+        ])
+        .await;
+        let config = ClientConfig::new("ak", "sk")
+            .endpoint(Endpoint::Custom(server.url.clone()))
+            .retry_policy(RetryPolicy {
+                base_delay: Duration::from_millis(1),
+                ..Default::default()
+            });
+        let client = TencentCloudClient::new(&config).unwrap();
+
+        // throttled requests are rejected before being applied, an internal error may come after
+        let err = client
+            .client
+            .call::<CreateThing>(&Region::Nanjing, &EmptyRequest {})
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Internal);
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_run_instances_client_token() {
+        let server = MockServer::start(vec![
+            json!({"Response": {"Error": {"Code": "InternalError", "Message": "oops"}, "RequestId": "req-1"}}),
+            json!({"Response": {"InstanceIdSet": ["ins-1"], "RequestId": "req-2"}}),
+        ])
+        .await;
+        let config = ClientConfig::new("ak", "sk")
+            .endpoint(Endpoint::Custom(server.url.clone()))
+            .retry_policy(RetryPolicy {
+                base_delay: Duration::from_millis(1),
+                ..Default::default()
+            });
+        let client = TencentCloudClient::new(&config).unwrap();
+
+        // retried as idempotent, the generated token keeps the resend from launching a second instance
+        let request = RunInstancesRequest::new("ap-nanjing-1", InstanceType::SA2Medium2);
+        let instance_id = client
+            .cvm()
+            .instances()
+            .run_instance(&Region::Nanjing, &request)
+            .await
+            .unwrap();
+        assert_eq!(instance_id, "ins-1");
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        let token = requests[0].json()["ClientToken"].clone();
+        assert!(
+            token
+                .as_str()
+                .is_some_and(|token| !token.is_empty() && token.len() <= 64)
+        );
+        assert_eq!(requests[1].json()["ClientToken"], token);
+    }
+
+    #[test]
+    fn test_endpoint_url() {
+        assert_eq!(
+            Endpoint::Default.url("cvm", "ap-nanjing"),
+            "https://cvm.tencentcloudapi.com"
+        );
+        assert_eq!(
+            Endpoint::Intl.url("cvm", "ap-nanjing"),
+            "https://cvm.intl.tencentcloudapi.com"
+        );
+        assert_eq!(
+            Endpoint::Regional.url("cvm", "ap-nanjing"),
+            "https://cvm.ap-nanjing.tencentcloudapi.com"
+        );
+    }
 }
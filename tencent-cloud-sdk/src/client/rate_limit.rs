@@ -0,0 +1,169 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use http::Extensions;
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next, Result};
+use tracing::debug;
+
+use crate::client::{constant::ACTION_HEADER, signature::SignatureContext};
+
+/// Per `service:action` qps limits, tencent cloud defaults to 20 qps for most apis.
+#[derive(Debug, Clone)]
+pub struct RateLimitPolicy {
+    /// None disables limiting for actions without an explicit limit
+    pub default_qps: Option<u32>,
+    /// `"cvm:InquiryPriceRunInstances" => 10`
+    pub limits: HashMap<String, u32>,
+}
+
+impl Default for RateLimitPolicy {
+    fn default() -> Self {
+        Self {
+            default_qps: Some(20),
+            limits: HashMap::new(),
+        }
+        .limit("cvm", "InquiryPriceRunInstances", 10)
+        .limit("cvm", "RunInstances", 10)
+    }
+}
+
+impl RateLimitPolicy {
+    pub fn disabled() -> Self {
+        Self {
+            default_qps: None,
+            limits: HashMap::new(),
+        }
+    }
+
+    pub fn limit(mut self, service: &str, action: &str, qps: u32) -> Self {
+        self.limits.insert(format!("{service}:{action}"), qps);
+        self
+    }
+
+    fn qps(&self, key: &str) -> Option<u32> {
+        self.limits
+            .get(key)
+            .copied()
+            .or(self.default_qps)
+            .filter(|qps| *qps > 0)
+    }
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    /// tokens per second, also the burst size
+    rate: f64,
+    /// goes negative when callers reserved tokens that aren't refilled yet
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    fn new(qps: u32, now: Instant) -> Self {
+        Self {
+            rate: qps as f64,
+            tokens: qps as f64,
+            last: now,
+        }
+    }
+
+    /// take one token, return how long the caller has to wait for it
+    fn reserve(&mut self, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.last = now;
+        self.tokens -= 1.0;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct RateLimiter {
+    policy: RateLimitPolicy,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(policy: RateLimitPolicy) -> Self {
+        Self {
+            policy,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn acquire(&self, service: &str, action: &str) {
+        let key = format!("{service}:{action}");
+        let Some(qps) = self.policy.qps(&key) else {
+            return;
+        };
+        let wait = {
+            let now = Instant::now();
+            let mut buckets = self.buckets.lock().unwrap();
+            buckets
+                .entry(key)
+                .or_insert_with(|| TokenBucket::new(qps, now))
+                .reserve(now)
+        };
+        if !wait.is_zero() {
+            debug!("rate limit {service}:{action} wait {wait:?}");
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// Registered after `RetryMiddleware`, so retries also take a token.
+pub struct RateLimitMiddleware {
+    pub limiter: RateLimiter,
+}
+
+#[async_trait]
+impl Middleware for RateLimitMiddleware {
+    async fn handle(&self, req: Request, extensions: &mut Extensions, next: Next<'_>) -> Result<Response> {
+        let action = req.headers().get(ACTION_HEADER).and_then(|v| v.to_str().ok());
+        if let (Some(context), Some(action)) = (extensions.get::<SignatureContext>(), action) {
+            self.limiter.acquire(&context.service_name, action).await;
+        }
+        next.run(req, extensions).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(10, now);
+        for _ in 0..10 {
+            assert_eq!(bucket.reserve(now), Duration::ZERO);
+        }
+        // burst used up, the 11th and 12th wait for the refill
+        assert_eq!(bucket.reserve(now), Duration::from_millis(100));
+        assert_eq!(bucket.reserve(now), Duration::from_millis(200));
+        // refilled after a second, but never more than the burst size
+        let later = now + Duration::from_secs(10);
+        for _ in 0..10 {
+            assert_eq!(bucket.reserve(later), Duration::ZERO);
+        }
+        assert!(bucket.reserve(later) > Duration::ZERO);
+    }
+
+    #[test]
+    fn test_policy() {
+        let policy = RateLimitPolicy::default().limit("vpc", "DescribeSecurityGroups", 5);
+        assert_eq!(policy.qps("cvm:InquiryPriceRunInstances"), Some(10));
+        assert_eq!(policy.qps("vpc:DescribeSecurityGroups"), Some(5));
+        assert_eq!(policy.qps("cvm:DescribeZones"), Some(20));
+        assert_eq!(RateLimitPolicy::disabled().qps("cvm:DescribeZones"), None);
+    }
+}
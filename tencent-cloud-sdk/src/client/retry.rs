@@ -0,0 +1,145 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use http::Extensions;
+use reqwest::{Request, Response, StatusCode};
+use reqwest_middleware::{Middleware, Next, Result};
+use tracing::warn;
+
+use crate::error::{ErrorKind, peek_api_error};
+
+/// How [`RetryMiddleware`] retries throttled (`RequestLimitExceeded`), `InternalError` and connection failures.
+/// Actions that aren't idempotent are only retried when throttled or not connected at all.
+///
+/// The delay before retry `n` (starting from 0) is a random duration in
+/// `[0, min(max_delay, base_delay * 2^n)]`.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// 0 disables retry
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn disabled() -> Self {
+        Self {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    fn backoff(&self, retry: u32) -> Duration {
+        let cap = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_delay);
+        cap.mul_f64(fastrand::f64())
+    }
+}
+
+/// Set on requests of actions that are not [`Action::IDEMPOTENT`](super::action::Action::IDEMPOTENT),
+/// they are only retried if the server surely didn't apply them: throttled or never connected.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct NotIdempotent;
+
+/// Must be registered before `SignatureMiddleware`, so every attempt passes
+/// through it and gets a fresh `X-TC-Timestamp` and signature.
+pub struct RetryMiddleware {
+    pub policy: RetryPolicy,
+}
+
+enum Attempt {
+    Done(Result<Response>),
+    Retry(String),
+}
+
+#[async_trait]
+impl Middleware for RetryMiddleware {
+    async fn handle(&self, req: Request, extensions: &mut Extensions, next: Next<'_>) -> Result<Response> {
+        let idempotent = extensions.get::<NotIdempotent>().is_none();
+        let mut retry = 0;
+        loop {
+            // body is always in memory json, try_clone only fails for streams
+            let Some(attempt_req) = req.try_clone().filter(|_| retry < self.policy.max_retries) else {
+                return next.run(req, extensions).await;
+            };
+            match attempt(next.clone().run(attempt_req, extensions).await, idempotent).await {
+                Attempt::Done(result) => return result,
+                Attempt::Retry(reason) => {
+                    let delay = self.policy.backoff(retry);
+                    retry += 1;
+                    warn!(
+                        "retry {retry}/{} {} after {delay:?}: {reason}",
+                        self.policy.max_retries,
+                        req.url()
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    }
+}
+
+async fn attempt(result: Result<Response>, idempotent: bool) -> Attempt {
+    let resp = match result {
+        Ok(resp) => resp,
+        Err(reqwest_middleware::Error::Reqwest(e)) if e.is_connect() || (idempotent && e.is_timeout()) => {
+            return Attempt::Retry(e.to_string());
+        }
+        Err(e) => return Attempt::Done(Err(e)),
+    };
+
+    let status = resp.status();
+    if (idempotent && status.is_server_error()) || status == StatusCode::TOO_MANY_REQUESTS {
+        return Attempt::Retry(format!("http status {status}"));
+    }
+
+    // api errors come with status 200, the body has to be read to find them,
+    // so the response is rebuilt from the buffered body afterwards
+    let mut builder = http::Response::builder().status(status).version(resp.version());
+    if let Some(headers) = builder.headers_mut() {
+        *headers = resp.headers().clone();
+    }
+    let body = match resp.bytes().await {
+        Ok(body) => body,
+        Err(e) if idempotent && e.is_timeout() => return Attempt::Retry(e.to_string()),
+        Err(e) => return Attempt::Done(Err(e.into())),
+    };
+    if let Some(error) = peek_api_error(&body)
+        && (error.kind() == ErrorKind::RateLimit || (idempotent && error.kind().is_retryable()))
+    {
+        return Attempt::Retry(format!("{} {}", error.code, error.message));
+    }
+    Attempt::Done(
+        builder
+            .body(body)
+            .map(Response::from)
+            .map_err(|e| reqwest_middleware::Error::Middleware(e.into())),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy::default();
+        for retry in 0..10 {
+            let cap = (policy.base_delay * 2u32.pow(retry)).min(policy.max_delay);
+            assert!(policy.backoff(retry) <= cap);
+        }
+        assert_eq!(RetryPolicy::disabled().max_retries, 0);
+    }
+}
@@ -12,12 +12,14 @@ use time::OffsetDateTime;
 
 use sha2::{Digest, Sha256};
 
-use crate::client::constant::ACTION_HEADER;
+use crate::client::constant::{ACTION_HEADER, TOKEN_HEADER};
 
 #[derive(Debug, Clone)]
 pub struct SignatureContext {
     pub ak: String,
     pub sk: String,
+    /// session token of temporary credentials
+    pub token: Option<String>,
     pub signed_headers: Option<Vec<HeaderName>>,
     pub service_name: String,
     pub version: String,
@@ -49,6 +51,9 @@ pub fn add_authorization(req: &mut Request, context: &SignatureContext) -> anyho
     let ts = OffsetDateTime::now_utc().unix_timestamp();
     req.headers_mut().insert(TIME_HEADER, ts.into());
     req.headers_mut().insert(TC_VERSION, context.version.parse().unwrap());
+    if let Some(token) = &context.token {
+        req.headers_mut().insert(TOKEN_HEADER, token.parse()?);
+    }
 
     let host = req.url().host_str().map(|s| s.to_owned());
     if let Some(host) = host {
@@ -180,6 +185,7 @@ mod tests {
         let signature_context = SignatureContext {
             ak: "ak".into(),
             sk: "sk".into(),
+            token: None,
             signed_headers: Some(vec![
                 HeaderName::from_str("content-type").unwrap(),
                 HeaderName::from_str("host").unwrap(),
@@ -0,0 +1,22 @@
+use std::sync::Arc;
+
+use super::TencentCloudBaseClient;
+
+pub mod sts_credential;
+
+const SERVICE: &str = "sts";
+const VERSION: &str = "2018-08-13";
+
+pub struct StsBuilder {
+    client: Arc<TencentCloudBaseClient>,
+}
+
+impl StsBuilder {
+    pub fn new(client: Arc<TencentCloudBaseClient>) -> Self {
+        Self { client }
+    }
+
+    pub fn credential(&self) -> sts_credential::StsCredentialBuilder {
+        sts_credential::StsCredentialBuilder::new(self.client.clone())
+    }
+}
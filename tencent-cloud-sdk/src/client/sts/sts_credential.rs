@@ -0,0 +1,231 @@
+use chrono::DateTime;
+use futures::FutureExt;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    client::action::impl_action,
+    constant::Region,
+    credential::{Credential, RefreshingCredentialProvider},
+    error::Result,
+};
+
+use super::*;
+
+pub struct StsCredentialBuilder {
+    client: Arc<TencentCloudBaseClient>,
+}
+
+/// AssumeRoleRequest
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AssumeRoleRequest {
+    /// `qcs::cam::uin/12345678:roleName/palworld`
+    pub role_arn: String,
+    pub role_session_name: String,
+    /// 900 ~ 43200, 7200 by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_seconds: Option<u64>,
+    /// further narrows the role's permissions, see [`Policy::encode`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub policy: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_id: Option<String>,
+}
+
+impl AssumeRoleRequest {
+    pub fn new(role_arn: impl Into<String>, role_session_name: impl Into<String>) -> Self {
+        Self {
+            role_arn: role_arn.into(),
+            role_session_name: role_session_name.into(),
+            duration_seconds: None,
+            policy: None,
+            external_id: None,
+        }
+    }
+    pub fn duration_seconds(mut self, duration_seconds: u64) -> Self {
+        self.duration_seconds = Some(duration_seconds);
+        self
+    }
+    pub fn policy(mut self, policy: &Policy) -> Self {
+        self.policy = Some(policy.encode());
+        self
+    }
+    pub fn external_id(mut self, external_id: impl Into<String>) -> Self {
+        self.external_id = Some(external_id.into());
+        self
+    }
+}
+
+/// GetFederationTokenRequest
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct GetFederationTokenRequest {
+    pub name: String,
+    /// required, see [`Policy::encode`]
+    pub policy: String,
+    /// 10 ~ 7200, 1800 by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_seconds: Option<u64>,
+}
+
+impl GetFederationTokenRequest {
+    pub fn new(name: impl Into<String>, policy: &Policy) -> Self {
+        Self {
+            name: name.into(),
+            policy: policy.encode(),
+            duration_seconds: None,
+        }
+    }
+    pub fn duration_seconds(mut self, duration_seconds: u64) -> Self {
+        self.duration_seconds = Some(duration_seconds);
+        self
+    }
+}
+
+/// AssumeRoleResponse / GetFederationTokenResponse
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct TemporaryCredentialResponse {
+    pub credentials: TemporaryCredentials,
+    /// unix timestamp
+    pub expired_time: i64,
+    /// ISO8601
+    pub expiration: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct TemporaryCredentials {
+    pub token: String,
+    pub tmp_secret_id: String,
+    pub tmp_secret_key: String,
+}
+
+impl From<TemporaryCredentialResponse> for Credential {
+    fn from(resp: TemporaryCredentialResponse) -> Self {
+        Credential::new(resp.credentials.tmp_secret_id, resp.credentials.tmp_secret_key)
+            .with_token(resp.credentials.token, DateTime::from_timestamp(resp.expired_time, 0))
+    }
+}
+
+/// CAM policy, e.g. `Policy::allow(["cvm:*", "vpc:*"])`
+#[derive(Debug, Clone, Serialize)]
+pub struct Policy {
+    pub version: String,
+    pub statement: Vec<Statement>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Statement {
+    pub effect: String,
+    pub action: Vec<String>,
+    pub resource: Vec<String>,
+}
+
+impl Policy {
+    /// allow `actions` on every resource
+    pub fn allow(actions: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            version: "2.0".to_owned(),
+            statement: vec![Statement {
+                effect: "allow".to_owned(),
+                action: actions.into_iter().map(Into::into).collect(),
+                resource: vec!["*".to_owned()],
+            }],
+        }
+    }
+
+    /// the api wants the policy json url-encoded
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_string(self).expect("policy is always serializable");
+        percent_encoding::utf8_percent_encode(&json, percent_encoding::NON_ALPHANUMERIC).to_string()
+    }
+}
+
+impl_action!(AssumeRole: SERVICE, VERSION, AssumeRoleRequest => TemporaryCredentialResponse, SENSITIVE = true);
+impl_action!(GetFederationToken: SERVICE, VERSION, GetFederationTokenRequest => TemporaryCredentialResponse, SENSITIVE = true);
+
+impl StsCredentialBuilder {
+    pub fn new(client: Arc<TencentCloudBaseClient>) -> Self {
+        Self { client }
+    }
+
+    pub async fn assume_role(&self, region: &Region, request: &AssumeRoleRequest) -> Result<Credential> {
+        let body = self.client.call::<AssumeRole>(region, request).await?;
+        Ok(body.into())
+    }
+
+    pub async fn get_federation_token(
+        &self,
+        region: &Region,
+        request: &GetFederationTokenRequest,
+    ) -> Result<Credential> {
+        let body = self.client.call::<GetFederationToken>(region, request).await?;
+        Ok(body.into())
+    }
+
+    /// Provider that assumes the role with this client's credentials, and again whenever the
+    /// temporary credentials are about to expire. Pass it to [`crate::config::ClientConfig::with_provider`].
+    pub fn assume_role_provider(&self, region: &Region, request: AssumeRoleRequest) -> RefreshingCredentialProvider {
+        let client = self.client.clone();
+        let region = region.clone();
+        RefreshingCredentialProvider::new(move || {
+            let client = client.clone();
+            let region = region.clone();
+            let request = request.clone();
+            async move { Ok(client.call::<AssumeRole>(&region, &request).await?.into()) }.boxed()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        client::{TencentCloudClient, mock::MockServer},
+        config::{ClientConfig, Endpoint},
+        credential::CredentialProvider,
+    };
+
+    #[test]
+    fn test_policy_encode() {
+        let policy = Policy::allow(["cvm:*", "vpc:*"]);
+        let decoded = percent_encoding::percent_decode_str(&policy.encode())
+            .decode_utf8()
+            .unwrap()
+            .to_string();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&decoded).unwrap(),
+            json!({"version": "2.0", "statement": [{"effect": "allow", "action": ["cvm:*", "vpc:*"], "resource": ["*"]}]})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_assume_role_provider() {
+        let server = MockServer::start(vec![json!({"Response": {
+            "Credentials": {"Token": "token", "TmpSecretId": "tmp-id", "TmpSecretKey": "tmp-key"},
+            "ExpiredTime": 4102444800i64,
+            "Expiration": "2100-01-01T00:00:00Z",
+            "RequestId": "req-1",
+        }})])
+        .await;
+        let config = ClientConfig::new("ak", "sk").endpoint(Endpoint::Custom(server.url.clone()));
+        let client = TencentCloudClient::new(&config).unwrap();
+
+        let provider = client.sts().credential().assume_role_provider(
+            &Region::Guangzhou,
+            AssumeRoleRequest::new("qcs::cam::uin/1:roleName/palworld", "psm"),
+        );
+        let credential = provider.credential().await.unwrap();
+        assert_eq!(credential.secret_id, "tmp-id");
+        assert_eq!(credential.token.as_deref(), Some("token"));
+        // cached until it is about to expire
+        provider.credential().await.unwrap();
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].header("x-tc-action"), Some("AssumeRole"));
+        assert_eq!(requests[0].json()["RoleSessionName"], "psm");
+    }
+}
@@ -1,7 +1,156 @@
-use serde::Deserialize;
+use std::{sync::Arc, time::Duration};
 
-#[derive(Debug, Deserialize, Clone)]
+use serde::{Deserialize, Deserializer};
+use strum::{Display, EnumString, IntoStaticStr};
+
+use crate::{
+    client::{RateLimitPolicy, RetryPolicy},
+    credential::{
+        ChainCredentialProvider, Credential, CredentialProvider, EnvCredentialProvider, ProfileCredentialProvider,
+        StaticCredentialProvider,
+    },
+};
+
+/// Credentials are taken from, in order: `credential_provider`, `ak`/`sk`, or
+/// the `TENCENTCLOUD_*` environment variables and then `profile` of `~/.tencentcloud/credentials`.
+#[derive(Debug, Deserialize, Clone, Default)]
 pub struct ClientConfig {
-    pub ak: String,
-    pub sk: String,
+    #[serde(default)]
+    pub ak: Option<String>,
+    #[serde(default)]
+    pub sk: Option<String>,
+    /// section of `~/.tencentcloud/credentials`, `default` if not set
+    #[serde(default)]
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub endpoint: Endpoint,
+    /// seconds in config file
+    #[serde(default, deserialize_with = "deserialize_secs")]
+    pub connect_timeout: Option<Duration>,
+    /// seconds in config file
+    #[serde(default, deserialize_with = "deserialize_secs")]
+    pub timeout: Option<Duration>,
+    /// e.g. `http://127.0.0.1:7890`
+    #[serde(default)]
+    pub proxy: Option<String>,
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    /// `X-TC-Language` of the error messages
+    #[serde(default)]
+    pub language: Option<Language>,
+    #[serde(skip)]
+    pub retry_policy: RetryPolicy,
+    #[serde(skip)]
+    pub rate_limit_policy: RateLimitPolicy,
+    #[serde(skip)]
+    pub credential_provider: Option<Arc<dyn CredentialProvider>>,
+}
+
+impl ClientConfig {
+    pub fn new(ak: impl Into<String>, sk: impl Into<String>) -> Self {
+        Self {
+            ak: Some(ak.into()),
+            sk: Some(sk.into()),
+            ..Default::default()
+        }
+    }
+    pub fn with_provider(provider: Arc<dyn CredentialProvider>) -> Self {
+        Self::default().credential_provider(provider)
+    }
+    pub fn credential_provider(mut self, provider: Arc<dyn CredentialProvider>) -> Self {
+        self.credential_provider = Some(provider);
+        self
+    }
+    pub fn profile(mut self, profile: impl Into<String>) -> Self {
+        self.profile = Some(profile.into());
+        self
+    }
+    pub fn endpoint(mut self, endpoint: Endpoint) -> Self {
+        self.endpoint = endpoint;
+        self
+    }
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+    pub fn proxy(mut self, proxy: impl Into<String>) -> Self {
+        self.proxy = Some(proxy.into());
+        self
+    }
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+    pub fn language(mut self, language: Language) -> Self {
+        self.language = Some(language);
+        self
+    }
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+    pub fn rate_limit_policy(mut self, rate_limit_policy: RateLimitPolicy) -> Self {
+        self.rate_limit_policy = rate_limit_policy;
+        self
+    }
+}
+
+impl ClientConfig {
+    pub(crate) fn build_credential_provider(&self) -> Arc<dyn CredentialProvider> {
+        if let Some(provider) = &self.credential_provider {
+            return provider.clone();
+        }
+        if let (Some(ak), Some(sk)) = (&self.ak, &self.sk) {
+            return Arc::new(StaticCredentialProvider(Credential::new(ak, sk)));
+        }
+        let profile = self.profile.as_deref().unwrap_or("default");
+        Arc::new(ChainCredentialProvider::new(vec![
+            Arc::new(EnvCredentialProvider),
+            Arc::new(ProfileCredentialProvider::new(profile)),
+        ]))
+    }
+}
+
+/// Where requests are sent, `endpoint = "intl"` or `endpoint = { custom = "http://127.0.0.1:8080" }` in config file.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Endpoint {
+    /// `https://{service}.tencentcloudapi.com`, routed to the nearest region
+    #[default]
+    Default,
+    /// `https://{service}.intl.tencentcloudapi.com`, for the international site
+    Intl,
+    /// `https://{service}.{region}.tencentcloudapi.com`, region of each request
+    Regional,
+    /// same url for every service and region, e.g. a local mock server
+    Custom(String),
+}
+
+impl Endpoint {
+    pub fn url(&self, service: &str, region: &str) -> String {
+        match self {
+            Endpoint::Default => format!("https://{service}.tencentcloudapi.com"),
+            Endpoint::Intl => format!("https://{service}.intl.tencentcloudapi.com"),
+            Endpoint::Regional => format!("https://{service}.{region}.tencentcloudapi.com"),
+            Endpoint::Custom(url) => url.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, EnumString, Display, IntoStaticStr, PartialEq, Eq)]
+pub enum Language {
+    #[serde(rename = "zh-CN")]
+    #[strum(serialize = "zh-CN")]
+    ZhCN,
+    #[serde(rename = "en-US")]
+    #[strum(serialize = "en-US")]
+    EnUS,
+}
+
+fn deserialize_secs<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+    Ok(Option::<u64>::deserialize(deserializer)?.map(Duration::from_secs))
 }
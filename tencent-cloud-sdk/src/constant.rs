@@ -1,13 +1,13 @@
+use std::str::FromStr;
 
-use serde::Serialize;
-// use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use strum::{Display, EnumString};
 
-#[derive(Debug, Clone, EnumString, Display, Serialize)]
+/// (de)serialized as the api name, e.g. `ap-nanjing`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, EnumString, Display)]
 pub enum Region {
     // 亚太东南（曼谷）
     #[strum(serialize = "ap-bangkok")]
-    #[serde(rename = "ap-bangkok")]
     Bangkok,
     // 华北地区（北京）
     #[strum(serialize = "ap-beijing")]
@@ -60,9 +60,13 @@ pub enum Region {
     // 南美地区（圣保罗）
     #[strum(serialize = "sa-saopaulo")]
     Saopaulo,
+    /// any region not listed above, see `DescribeRegions`
+    #[strum(default)]
+    Other(String),
 }
 
-#[derive(Debug, Clone, EnumString, Display, Serialize)]
+/// (de)serialized as the api name, e.g. `SA2.MEDIUM2`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, EnumString, Display)]
 pub enum InstanceType {
     #[strum(serialize = "SA2.MEDIUM2")]
     SA2Medium2, // 2C2G // for test case
@@ -106,4 +110,59 @@ pub enum InstanceType {
 
     #[strum(serialize = "SA9.LARGE16")]
     SA9Large16, // 4C16G
-}
\ No newline at end of file
+
+    /// any type not listed above, see `DescribeZoneInstanceConfigInfos`
+    #[strum(default)]
+    Other(String),
+}
+
+/// through `Display` / `FromStr`, so serde and strum always agree
+macro_rules! impl_serde_by_str {
+    ($($name:ident),*) => {
+        $(
+            impl Serialize for $name {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.collect_str(self)
+                }
+            }
+
+            impl<'de> Deserialize<'de> for $name {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    let s = String::deserialize(deserializer)?;
+                    // never fails, unknown names go to `Other`
+                    $name::from_str(&s).map_err(serde::de::Error::custom)
+                }
+            }
+        )*
+    };
+}
+
+impl_serde_by_str!(Region, InstanceType);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serde_round_trip() {
+        for (region, name) in [
+            (Region::Beijing, "ap-beijing"),
+            (Region::Bangkok, "ap-bangkok"),
+            (Region::Other("ap-new-city".to_owned()), "ap-new-city"),
+        ] {
+            assert_eq!(serde_json::to_value(&region).unwrap(), name);
+            assert_eq!(region.to_string(), name);
+            assert_eq!(serde_json::from_value::<Region>(name.into()).unwrap(), region);
+        }
+        for (instance_type, name) in [
+            (InstanceType::SA2Medium2, "SA2.MEDIUM2"),
+            (InstanceType::Other("SA9.8XLARGE128".to_owned()), "SA9.8XLARGE128"),
+        ] {
+            assert_eq!(serde_json::to_value(&instance_type).unwrap(), name);
+            assert_eq!(
+                serde_json::from_value::<InstanceType>(name.into()).unwrap(),
+                instance_type
+            );
+        }
+    }
+}
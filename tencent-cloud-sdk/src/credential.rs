@@ -0,0 +1,311 @@
+use std::{fmt, path::PathBuf, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use tokio::sync::Mutex;
+use tracing::debug;
+
+use crate::error::{Result, TencentCloudError};
+
+pub const SECRET_ID_ENV: &str = "TENCENTCLOUD_SECRET_ID";
+pub const SECRET_KEY_ENV: &str = "TENCENTCLOUD_SECRET_KEY";
+pub const SESSION_TOKEN_ENV: &str = "TENCENTCLOUD_SESSION_TOKEN";
+
+#[derive(Clone)]
+pub struct Credential {
+    pub secret_id: String,
+    pub secret_key: String,
+    /// sent as `X-TC-Token`, only for temporary credentials
+    pub token: Option<String>,
+    /// None for long-lived keys
+    pub expired_time: Option<DateTime<Utc>>,
+}
+
+impl fmt::Debug for Credential {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credential")
+            .field("secret_id", &self.secret_id)
+            .field("token", &self.token.as_ref().map(|_| "***"))
+            .field("expired_time", &self.expired_time)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Credential {
+    pub fn new(secret_id: impl Into<String>, secret_key: impl Into<String>) -> Self {
+        Self {
+            secret_id: secret_id.into(),
+            secret_key: secret_key.into(),
+            token: None,
+            expired_time: None,
+        }
+    }
+
+    pub fn with_token(mut self, token: impl Into<String>, expired_time: Option<DateTime<Utc>>) -> Self {
+        self.token = Some(token.into());
+        self.expired_time = expired_time;
+        self
+    }
+
+    /// expired, or will be within `margin`
+    pub fn expires_within(&self, margin: Duration) -> bool {
+        self.expired_time
+            .is_some_and(|t| t - chrono::Duration::from_std(margin).unwrap_or(chrono::Duration::MAX) <= Utc::now())
+    }
+}
+
+/// Source of the keys used to sign every request, asked again before each call.
+#[async_trait]
+pub trait CredentialProvider: fmt::Debug + Send + Sync {
+    async fn credential(&self) -> Result<Credential>;
+}
+
+/// keys from config
+#[derive(Debug, Clone)]
+pub struct StaticCredentialProvider(pub Credential);
+
+#[async_trait]
+impl CredentialProvider for StaticCredentialProvider {
+    async fn credential(&self) -> Result<Credential> {
+        Ok(self.0.clone())
+    }
+}
+
+/// `TENCENTCLOUD_SECRET_ID` / `TENCENTCLOUD_SECRET_KEY` / `TENCENTCLOUD_SESSION_TOKEN`
+#[derive(Debug, Clone, Default)]
+pub struct EnvCredentialProvider;
+
+#[async_trait]
+impl CredentialProvider for EnvCredentialProvider {
+    async fn credential(&self) -> Result<Credential> {
+        let var = |name| std::env::var(name).ok().filter(|v| !v.is_empty());
+        let (Some(secret_id), Some(secret_key)) = (var(SECRET_ID_ENV), var(SECRET_KEY_ENV)) else {
+            return Err(TencentCloudError::Credential(format!(
+                "{SECRET_ID_ENV} or {SECRET_KEY_ENV} not set"
+            )));
+        };
+        let credential = Credential::new(secret_id, secret_key);
+        Ok(match var(SESSION_TOKEN_ENV) {
+            Some(token) => credential.with_token(token, None),
+            None => credential,
+        })
+    }
+}
+
+/// A section of `~/.tencentcloud/credentials`:
+///
+/// ```ini
+/// [default]
+/// secret_id = AKID...
+/// secret_key = ...
+/// ```
+#[derive(Debug, Clone)]
+pub struct ProfileCredentialProvider {
+    pub path: PathBuf,
+    pub profile: String,
+}
+
+impl ProfileCredentialProvider {
+    pub fn new(profile: impl Into<String>) -> Self {
+        let home = std::env::var_os("HOME")
+            .or_else(|| std::env::var_os("USERPROFILE"))
+            .unwrap_or_default();
+        Self {
+            path: PathBuf::from(home).join(".tencentcloud").join("credentials"),
+            profile: profile.into(),
+        }
+    }
+}
+
+impl Default for ProfileCredentialProvider {
+    fn default() -> Self {
+        Self::new("default")
+    }
+}
+
+#[async_trait]
+impl CredentialProvider for ProfileCredentialProvider {
+    async fn credential(&self) -> Result<Credential> {
+        let content = tokio::fs::read_to_string(&self.path)
+            .await
+            .map_err(|e| TencentCloudError::Credential(format!("read {:?} failed: {e}", self.path)))?;
+        parse_profile(&content, &self.profile).ok_or_else(|| {
+            TencentCloudError::Credential(format!(
+                "profile [{}] without secret_id/secret_key in {:?}",
+                self.profile, self.path
+            ))
+        })
+    }
+}
+
+fn parse_profile(content: &str, profile: &str) -> Option<Credential> {
+    let (mut secret_id, mut secret_key, mut token) = (None, None, None);
+    let mut in_profile = false;
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            in_profile = section.trim() == profile;
+            continue;
+        }
+        if !in_profile {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            let value = Some(value.trim().to_owned());
+            match key.trim() {
+                "secret_id" => secret_id = value,
+                "secret_key" => secret_key = value,
+                "token" => token = value,
+                _ => {}
+            }
+        }
+    }
+    let credential = Credential::new(secret_id?, secret_key?);
+    Some(match token {
+        Some(token) => credential.with_token(token, None),
+        None => credential,
+    })
+}
+
+/// First provider that returns a credential wins.
+#[derive(Debug, Clone)]
+pub struct ChainCredentialProvider {
+    pub providers: Vec<Arc<dyn CredentialProvider>>,
+}
+
+impl ChainCredentialProvider {
+    pub fn new(providers: Vec<Arc<dyn CredentialProvider>>) -> Self {
+        Self { providers }
+    }
+}
+
+impl Default for ChainCredentialProvider {
+    /// environment variables, then the `default` profile
+    fn default() -> Self {
+        Self::new(vec![
+            Arc::new(EnvCredentialProvider),
+            Arc::new(ProfileCredentialProvider::default()),
+        ])
+    }
+}
+
+#[async_trait]
+impl CredentialProvider for ChainCredentialProvider {
+    async fn credential(&self) -> Result<Credential> {
+        let mut errors = vec![];
+        for provider in &self.providers {
+            match provider.credential().await {
+                Ok(credential) => return Ok(credential),
+                Err(e) => errors.push(e.to_string()),
+            }
+        }
+        Err(TencentCloudError::Credential(format!(
+            "no credential found: {}",
+            errors.join("; ")
+        )))
+    }
+}
+
+type FetchCredential = Box<dyn Fn() -> BoxFuture<'static, Result<Credential>> + Send + Sync>;
+
+/// Temporary credentials (e.g. from STS) cached until they are about to expire.
+pub struct RefreshingCredentialProvider {
+    fetch: FetchCredential,
+    cached: Mutex<Option<Credential>>,
+    /// refresh this long before `expired_time`
+    refresh_margin: Duration,
+}
+
+impl fmt::Debug for RefreshingCredentialProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RefreshingCredentialProvider")
+            .field("refresh_margin", &self.refresh_margin)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RefreshingCredentialProvider {
+    pub fn new<F>(fetch: F) -> Self
+    where
+        F: Fn() -> BoxFuture<'static, Result<Credential>> + Send + Sync + 'static,
+    {
+        Self {
+            fetch: Box::new(fetch),
+            cached: Mutex::new(None),
+            refresh_margin: Duration::from_secs(300),
+        }
+    }
+
+    pub fn refresh_margin(mut self, margin: Duration) -> Self {
+        self.refresh_margin = margin;
+        self
+    }
+}
+
+#[async_trait]
+impl CredentialProvider for RefreshingCredentialProvider {
+    async fn credential(&self) -> Result<Credential> {
+        let mut cached = self.cached.lock().await;
+        if let Some(credential) = cached.as_ref()
+            && !credential.expires_within(self.refresh_margin)
+        {
+            return Ok(credential.clone());
+        }
+        let credential = (self.fetch)().await?;
+        debug!("refreshed credential, expired at {:?}", credential.expired_time);
+        *cached = Some(credential.clone());
+        Ok(credential)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use futures::FutureExt;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_profile() {
+        let content = "
+            [default]
+            secret_id = id-default
+            secret_key = key-default
+
+            # team sub-account
+            [palworld]
+            secret_id = id-pal
+            secret_key = key-pal
+            token = token-pal
+        ";
+        let credential = parse_profile(content, "default").unwrap();
+        assert_eq!((credential.secret_id.as_str(), credential.token), ("id-default", None));
+        let credential = parse_profile(content, "palworld").unwrap();
+        assert_eq!(credential.secret_key, "key-pal");
+        assert_eq!(credential.token.as_deref(), Some("token-pal"));
+        assert!(parse_profile(content, "missing").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_refreshing_provider() {
+        let fetched = Arc::new(AtomicUsize::new(0));
+        let counter = fetched.clone();
+        let provider = RefreshingCredentialProvider::new(move || {
+            let n = counter.fetch_add(1, Ordering::SeqCst);
+            async move {
+                // the first one is about to expire, the second one lasts
+                let expired_time = Utc::now() + chrono::Duration::seconds(if n == 0 { 10 } else { 3600 });
+                Ok(Credential::new("id", "key").with_token(format!("token-{n}"), Some(expired_time)))
+            }
+            .boxed()
+        });
+        assert_eq!(provider.credential().await.unwrap().token.as_deref(), Some("token-0"));
+        assert_eq!(provider.credential().await.unwrap().token.as_deref(), Some("token-1"));
+        assert_eq!(provider.credential().await.unwrap().token.as_deref(), Some("token-1"));
+        assert_eq!(fetched.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,222 @@
+use std::fmt;
+
+use reqwest::StatusCode;
+use serde::{Deserialize, de::DeserializeOwned};
+
+pub type Result<T> = std::result::Result<T, TencentCloudError>;
+
+#[derive(Debug)]
+pub enum TencentCloudError {
+    /// `Response.Error` returned by the api, usually with http status 200
+    Api(ApiError),
+    /// non-200 http status without a parsable `Response.Error`
+    Http { status: StatusCode, body: String },
+    /// request never got a response
+    Transport(reqwest_middleware::Error),
+    /// response body doesn't match the expected struct
+    Decode(serde_json::Error),
+    /// no usable credential from the provider
+    Credential(String),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ApiError {
+    pub code: String,
+    pub message: String,
+    #[serde(default)]
+    pub request_id: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// AuthFailure.* / UnauthorizedOperation.*
+    Auth,
+    /// RequestLimitExceeded.*
+    RateLimit,
+    /// ResourcesSoldOut.* / ResourceInsufficient.* / ResourceUnavailable.*
+    ResourceSoldOut,
+    /// InvalidParameter* / MissingParameter / UnknownParameter / InvalidZone / InvalidRegion
+    InvalidParameter,
+    /// LimitExceeded.*
+    Quota,
+    /// InternalError.*
+    Internal,
+    Other,
+}
+
+impl ApiError {
+    pub fn kind(&self) -> ErrorKind {
+        ErrorKind::from_code(&self.code)
+    }
+}
+
+impl ErrorKind {
+    pub fn from_code(code: &str) -> Self {
+        let category = code.split('.').next().unwrap_or_default();
+        match category {
+            "AuthFailure" | "UnauthorizedOperation" => ErrorKind::Auth,
+            "RequestLimitExceeded" => ErrorKind::RateLimit,
+            "ResourcesSoldOut" | "ResourceInsufficient" | "ResourceUnavailable" => ErrorKind::ResourceSoldOut,
+            "InvalidParameter"
+            | "InvalidParameterValue"
+            | "InvalidParameterCombination"
+            | "MissingParameter"
+            | "UnknownParameter"
+            | "InvalidZone"
+            | "InvalidRegion" => ErrorKind::InvalidParameter,
+            "LimitExceeded" => ErrorKind::Quota,
+            "InternalError" => ErrorKind::Internal,
+            _ => ErrorKind::Other,
+        }
+    }
+
+    /// worth sending the same request again after a while
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, ErrorKind::RateLimit | ErrorKind::Internal)
+    }
+}
+
+impl TencentCloudError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            TencentCloudError::Api(e) => e.kind(),
+            TencentCloudError::Http { status, .. } => match *status {
+                StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => ErrorKind::Auth,
+                StatusCode::TOO_MANY_REQUESTS => ErrorKind::RateLimit,
+                _ => ErrorKind::Other,
+            },
+            TencentCloudError::Credential(_) => ErrorKind::Auth,
+            TencentCloudError::Transport(_) | TencentCloudError::Decode(_) => ErrorKind::Other,
+        }
+    }
+
+    pub fn code(&self) -> Option<&str> {
+        match self {
+            TencentCloudError::Api(e) => Some(&e.code),
+            _ => None,
+        }
+    }
+
+    pub fn request_id(&self) -> Option<&str> {
+        match self {
+            TencentCloudError::Api(e) => Some(&e.request_id),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TencentCloudError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TencentCloudError::Api(e) => write!(f, "[{}] {} (RequestId: {})", e.code, e.message, e.request_id),
+            TencentCloudError::Http { status, body } => write!(f, "err get code {status}, msg {body}"),
+            TencentCloudError::Transport(e) => write!(f, "request failed: {e}"),
+            TencentCloudError::Decode(e) => write!(f, "decode response failed: {e}"),
+            TencentCloudError::Credential(msg) => write!(f, "credential error: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for TencentCloudError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TencentCloudError::Transport(e) => Some(e),
+            TencentCloudError::Decode(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest_middleware::Error> for TencentCloudError {
+    fn from(e: reqwest_middleware::Error) -> Self {
+        TencentCloudError::Transport(e)
+    }
+}
+
+impl From<reqwest::Error> for TencentCloudError {
+    fn from(e: reqwest::Error) -> Self {
+        TencentCloudError::Transport(e.into())
+    }
+}
+
+impl From<serde_json::Error> for TencentCloudError {
+    fn from(e: serde_json::Error) -> Self {
+        TencentCloudError::Decode(e)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ErrorEnvelope {
+    response: ErrorEnvelopeInner,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ErrorEnvelopeInner {
+    error: Option<ApiError>,
+    #[serde(default)]
+    request_id: String,
+}
+
+/// `Response.Error` of the body if there is one
+pub(crate) fn peek_api_error(body: &[u8]) -> Option<ApiError> {
+    let envelope = serde_json::from_slice::<ErrorEnvelope>(body).ok()?;
+    let mut error = envelope.response.error?;
+    if error.request_id.is_empty() {
+        error.request_id = envelope.response.request_id;
+    }
+    Some(error)
+}
+
+/// check `Response.Error` before decoding the body into `T`
+pub(crate) fn parse_body<T: DeserializeOwned>(status: StatusCode, body: &str) -> Result<T> {
+    if let Some(error) = peek_api_error(body.as_bytes()) {
+        return Err(TencentCloudError::Api(error));
+    }
+    if status != StatusCode::OK {
+        return Err(TencentCloudError::Http {
+            status,
+            body: body.to_owned(),
+        });
+    }
+    Ok(serde_json::from_str(body)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_api_error() {
+        let body = r#"{"Response":{"Error":{"Code":"ResourcesSoldOut.SpecifiedInstanceType","Message":"sold out"},"RequestId":"req-1"}}"#;
+        let err = parse_body::<Value>(StatusCode::OK, body).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ResourceSoldOut);
+        assert_eq!(err.code(), Some("ResourcesSoldOut.SpecifiedInstanceType"));
+        assert_eq!(err.request_id(), Some("req-1"));
+    }
+
+    #[test]
+    fn test_error_kind_from_code() {
+        assert_eq!(ErrorKind::from_code("AuthFailure.SignatureFailure"), ErrorKind::Auth);
+        assert_eq!(ErrorKind::from_code("RequestLimitExceeded"), ErrorKind::RateLimit);
+        assert_eq!(ErrorKind::from_code("LimitExceeded.SpotQuota"), ErrorKind::Quota);
+        assert_eq!(
+            ErrorKind::from_code("InvalidParameterValue.Range"),
+            ErrorKind::InvalidParameter
+        );
+        assert_eq!(ErrorKind::from_code("InternalError.UnknownError"), ErrorKind::Internal);
+        assert_eq!(ErrorKind::from_code("FailedOperation"), ErrorKind::Other);
+    }
+
+    #[test]
+    fn test_parse_ok_and_http_error() {
+        let body = r#"{"Response":{"TotalCount":0,"RequestId":"req-2"}}"#;
+        assert!(parse_body::<Value>(StatusCode::OK, body).is_ok());
+        let err = parse_body::<Value>(StatusCode::BAD_GATEWAY, "bad gateway").unwrap_err();
+        assert!(matches!(err, TencentCloudError::Http { .. }));
+    }
+}
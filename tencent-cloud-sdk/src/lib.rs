@@ -1,3 +1,5 @@
 pub mod client;
 pub mod config;
-pub mod constant;
\ No newline at end of file
+pub mod constant;
+pub mod credential;
+pub mod error;
\ No newline at end of file